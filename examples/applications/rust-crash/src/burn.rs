@@ -0,0 +1,161 @@
+//! Sustained CPU load for exercising `least-cpu` balancing and `metrics:cpu-high`.
+//!
+//! Each burn thread runs a duty cycle: it spins for `pct`% of every period and
+//! sleeps for the rest. Starting a new burn supersedes the previous one.
+
+use std::hint::black_box;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::http::{Request, Response};
+
+const PERIOD: Duration = Duration::from_millis(100);
+/// Burn threads allowed per available core.
+const THREADS_PER_CORE: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Burn {
+    threads: usize,
+    pct: u8,
+    secs: u64,
+}
+
+impl Burn {
+    fn describe(&self) -> String {
+        let duration = if self.secs == 0 {
+            "until stopped".to_string()
+        } else {
+            format!("for {}s", self.secs)
+        };
+        format!(
+            "{} thread(s) at {}% duty cycle {}",
+            self.threads, self.pct, duration
+        )
+    }
+}
+
+/// Bumped on every start/stop; burn threads exit once it moves past theirs.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static CURRENT: Mutex<Option<Burn>> = Mutex::new(None);
+
+/// Most burn threads accepted at once: enough to saturate every core several
+/// times over without running the process out of threads.
+fn max_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get()) * THREADS_PER_CORE
+}
+
+/// Start burning, replacing any burn already running. `threads == 0` stops.
+/// If a thread cannot be created the partial burn is stopped again.
+fn start(burn: Burn) -> Result<(), String> {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = (burn.threads > 0).then_some(burn);
+    }
+
+    if burn.threads == 0 {
+        info!("🔥 CPU burn stopped");
+        return Ok(());
+    }
+
+    info!("🔥 CPU burn: {}", burn.describe());
+    if let Err(e) = spawn_threads(burn, generation) {
+        // Stop whichever threads did start.
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut current) = CURRENT.lock() {
+            *current = None;
+        }
+        return Err(format!("cannot start {} burn thread(s): {}", burn.threads, e));
+    }
+    Ok(())
+}
+
+fn spawn_threads(burn: Burn, generation: u64) -> io::Result<()> {
+    let deadline = (burn.secs > 0).then(|| Instant::now() + Duration::from_secs(burn.secs));
+    let busy = PERIOD * u32::from(burn.pct) / 100;
+
+    for _ in 0..burn.threads {
+        thread::Builder::new().name("burn".to_string()).spawn(move || {
+            while GENERATION.load(Ordering::SeqCst) == generation {
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break;
+                }
+                let start = Instant::now();
+                while start.elapsed() < busy {
+                    black_box(spin());
+                }
+                thread::sleep(PERIOD.saturating_sub(busy));
+            }
+        })?;
+    }
+
+    if let Some(deadline) = deadline {
+        thread::Builder::new().spawn(move || {
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            if GENERATION.load(Ordering::SeqCst) == generation {
+                if let Ok(mut current) = CURRENT.lock() {
+                    *current = None;
+                }
+                info!("🔥 CPU burn finished");
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// Start the burn configured by `BURN_THREADS`, `BURN_PCT` and `BURN_SECS`.
+pub fn start_from_env() {
+    let threads: usize = config::parse_or("BURN_THREADS", 0);
+    if threads == 0 {
+        return;
+    }
+    if threads > max_threads() {
+        warn!("Ignoring BURN_THREADS {} (at most {} on this machine)", threads, max_threads());
+        return;
+    }
+    let burn = Burn {
+        threads,
+        pct: config::parse_or("BURN_PCT", 100u8).min(100),
+        secs: config::parse_or("BURN_SECS", 0),
+    };
+    if let Err(e) = start(burn) {
+        error!("CPU burn failed: {}", e);
+    }
+}
+
+/// `GET /burn?threads=N&pct=P&secs=S`
+pub fn handle(request: &Request) -> Result<Response, Response> {
+    let Some(threads) = request.parse_query::<usize>("threads")? else {
+        let status = match CURRENT.lock().ok().and_then(|c| *c) {
+            Some(burn) => format!("Burning {}", burn.describe()),
+            None => "Not burning".to_string(),
+        };
+        return Ok(Response::ok(status));
+    };
+    if threads > max_threads() {
+        return Err(Response::bad_request(format!("threads must be at most {}", max_threads())));
+    }
+    let pct = request.parse_query::<u8>("pct")?.unwrap_or(100);
+    if pct > 100 {
+        return Err(Response::bad_request("pct must be between 0 and 100"));
+    }
+    let secs = request.parse_query("secs")?.unwrap_or(0);
+
+    let burn = Burn { threads, pct, secs };
+    if let Err(e) = start(burn) {
+        error!("CPU burn failed: {}", e);
+        return Ok(Response::text(500, e));
+    }
+    if threads == 0 {
+        Ok(Response::ok("CPU burn stopped"))
+    } else {
+        Ok(Response::ok(format!("Burning {}", burn.describe())))
+    }
+}
+
+fn spin() -> u64 {
+    (0..1_000u64).fold(0, |acc, x| acc.wrapping_mul(31).wrapping_add(x))
+}
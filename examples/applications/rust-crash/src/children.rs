@@ -0,0 +1,292 @@
+//! Optional tree of worker processes, for showing whether stopping or
+//! restarting an app under TSPM leaves orphaned native workers behind.
+//!
+//! Workers are re-executions of this binary with `RUST_CRASH_ROLE` set. A child
+//! spawns its grandchildren and reports their PIDs back over its stdout; both
+//! then idle until killed. Each process can stay in its parent's process group
+//! (`none`), lead a new group (`group`) or a new session (`session`).
+
+use std::env;
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::process::CommandExt;
+use std::process::{self, Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::json;
+use crate::sys;
+
+const ROLE_VAR: &str = "RUST_CRASH_ROLE";
+const GRANDCHILD_MARKER: &str = "GRANDCHILD";
+/// Most children one request (or `SPAWN_CHILDREN`) may start.
+const MAX_CHILDREN: usize = 64;
+/// Most grandchildren each child may start.
+const MAX_GRANDCHILDREN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Isolation {
+    None,
+    Group,
+    Session,
+}
+
+impl Isolation {
+    fn name(self) -> &'static str {
+        match self {
+            Isolation::None => "none",
+            Isolation::Group => "group",
+            Isolation::Session => "session",
+        }
+    }
+
+    fn apply(self, command: &mut Command) {
+        match self {
+            Isolation::None => {}
+            Isolation::Group => {
+                command.process_group(0);
+            }
+            Isolation::Session => {
+                // SAFETY: `setsid` is async-signal-safe and touches no memory.
+                unsafe { command.pre_exec(sys::new_session) };
+            }
+        }
+    }
+}
+
+impl FromStr for Isolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(Isolation::None),
+            "group" | "pgid" => Ok(Isolation::Group),
+            "session" | "setsid" => Ok(Isolation::Session),
+            other => Err(format!(
+                "unknown isolation '{}' (expected none, group or session)",
+                other
+            )),
+        }
+    }
+}
+
+/// Comma-separated isolations assigned round-robin, e.g. `none,group,session`.
+fn parse_isolations(spec: &str) -> Result<Vec<Isolation>, String> {
+    let list = spec
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(if list.is_empty() {
+        vec![Isolation::None]
+    } else {
+        list
+    })
+}
+
+struct Grandchild {
+    pid: u32,
+    isolation: String,
+}
+
+struct Worker {
+    child: Child,
+    isolation: Isolation,
+    grandchildren: Arc<Mutex<Vec<Grandchild>>>,
+}
+
+static WORKERS: Mutex<Vec<Worker>> = Mutex::new(Vec::new());
+
+/// Spawn one child that in turn spawns `grandchildren` of its own.
+fn spawn_child(isolation: Isolation, grandchildren: usize, grand_isolation: &str) -> io::Result<u32> {
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .env(ROLE_VAR, "child")
+        .env("SPAWN_GRANDCHILDREN", grandchildren.to_string())
+        .env("GRANDCHILD_ISOLATION", grand_isolation)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped());
+    isolation.apply(&mut command);
+
+    let mut child = command.spawn()?;
+    let pid = child.id();
+    let reported = Arc::new(Mutex::new(Vec::new()));
+
+    if let Some(stdout) = child.stdout.take() {
+        let reported = Arc::clone(&reported);
+        let reader = thread::Builder::new().spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let mut parts = line.split_whitespace();
+                if parts.next() == Some(GRANDCHILD_MARKER) {
+                    let grandchild = parts.next().and_then(|p| p.parse().ok());
+                    if let (Some(gpid), Ok(mut list)) = (grandchild, reported.lock()) {
+                        list.push(Grandchild {
+                            pid: gpid,
+                            isolation: parts.next().unwrap_or("none").to_string(),
+                        });
+                    }
+                } else {
+                    info!("[child {}] {}", pid, line);
+                }
+            }
+        });
+        if let Err(e) = reader {
+            // Without a reader the child would block on a full pipe.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    }
+
+    info!("👶 Spawned child {} ({})", pid, isolation.name());
+    if let Ok(mut workers) = WORKERS.lock() {
+        workers.push(Worker {
+            child,
+            isolation,
+            grandchildren: reported,
+        });
+    }
+    Ok(pid)
+}
+
+fn spawn_children(count: usize, isolations: &[Isolation], grandchildren: usize, grand_isolation: &str) -> Vec<u32> {
+    (0..count)
+        .filter_map(|i| {
+            let isolation = isolations[i % isolations.len()];
+            spawn_child(isolation, grandchildren, grand_isolation)
+                .map_err(|e| error!("Failed to spawn child: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Keep a tree within `MAX_CHILDREN` and `MAX_GRANDCHILDREN`, so a typo cannot
+/// fork the machine out of PIDs.
+fn check_counts(children: usize, grandchildren: usize) -> Result<(), String> {
+    if children > MAX_CHILDREN {
+        return Err(format!("cannot spawn {} children (at most {} at once)", children, MAX_CHILDREN));
+    }
+    if grandchildren > MAX_GRANDCHILDREN {
+        return Err(format!("cannot give each child {} grandchildren (at most {})", grandchildren, MAX_GRANDCHILDREN));
+    }
+    Ok(())
+}
+
+/// Spawn the tree configured by `SPAWN_CHILDREN` and friends.
+pub fn start_from_env() {
+    let count: usize = config::parse_or("SPAWN_CHILDREN", 0);
+    if count == 0 {
+        return;
+    }
+    let grandchildren: usize = config::parse_or("SPAWN_GRANDCHILDREN", 0);
+    if let Err(e) = check_counts(count, grandchildren) {
+        error!("Not spawning workers: {}", e);
+        return;
+    }
+    let isolations = match parse_isolations(&config::var("CHILD_ISOLATION").unwrap_or_default()) {
+        Ok(list) => list,
+        Err(e) => {
+            error!("Invalid CHILD_ISOLATION: {}", e);
+            return;
+        }
+    };
+    spawn_children(
+        count,
+        &isolations,
+        grandchildren,
+        &config::var("GRANDCHILD_ISOLATION").unwrap_or_default(),
+    );
+}
+
+/// If this process is a worker, run its role and never return.
+pub fn run_role() {
+    let Ok(role) = env::var(ROLE_VAR) else {
+        return;
+    };
+
+    if role == "child" {
+        let count = config::parse_or("SPAWN_GRANDCHILDREN", 0).min(MAX_GRANDCHILDREN);
+        let isolations = parse_isolations(&config::var("GRANDCHILD_ISOLATION").unwrap_or_default())
+            .unwrap_or_else(|_| vec![Isolation::None]);
+        for i in 0..count {
+            let isolation = isolations[i % isolations.len()];
+            let mut command = match env::current_exe() {
+                Ok(exe) => Command::new(exe),
+                Err(_) => break,
+            };
+            command
+                .env(ROLE_VAR, "grandchild")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null());
+            isolation.apply(&mut command);
+            match command.spawn() {
+                // Reported to the parent, which parses this line.
+                Ok(grandchild) => println!("{} {} {}", GRANDCHILD_MARKER, grandchild.id(), isolation.name()),
+                Err(e) => eprintln!("Failed to spawn grandchild: {}", e),
+            }
+        }
+    }
+
+    // Idle until killed; never reap anything so orphans stay visible.
+    loop {
+        thread::sleep(Duration::from_secs(3600));
+    }
+}
+
+/// `GET /children[?spawn=N&grandchildren=M&isolation=none,group,session&grandchild_isolation=...]`
+pub fn handle(request: &Request) -> Result<Response, Response> {
+    if let Some(count) = request.parse_query::<usize>("spawn")? {
+        let grandchildren = request.parse_query("grandchildren")?.unwrap_or(0);
+        check_counts(count, grandchildren).map_err(Response::bad_request)?;
+        let isolations = parse_isolations(request.query("isolation").unwrap_or("none")).map_err(Response::bad_request)?;
+        let grand_isolation = request.query("grandchild_isolation").unwrap_or("none");
+        parse_isolations(grand_isolation).map_err(Response::bad_request)?;
+        spawn_children(count, &isolations, grandchildren, grand_isolation);
+        // Give the children a moment to report their grandchildren.
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    Ok(Response::json(200, tree_json()))
+}
+
+fn tree_json() -> String {
+    let Ok(mut workers) = WORKERS.lock() else {
+        return "{}".to_string();
+    };
+
+    let children: Vec<String> = workers
+        .iter_mut()
+        .map(|worker| {
+            let pid = worker.child.id();
+            let exited = worker.child.try_wait().ok().flatten();
+            let grandchildren: Vec<String> = worker
+                .grandchildren
+                .lock()
+                .map(|list| {
+                    list.iter()
+                        .map(|g| {
+                            json::Object::new()
+                                .num("pid", g.pid)
+                                .str("isolation", &g.isolation)
+                                .raw("alive", sys::process_alive(g.pid).to_string())
+                                .render()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            json::Object::new()
+                .num("pid", pid)
+                .str("isolation", worker.isolation.name())
+                .raw("alive", exited.is_none().to_string())
+                .raw("grandchildren", format!("[{}]", grandchildren.join(",")))
+                .render()
+        })
+        .collect();
+
+    json::Object::new()
+        .num("pid", process::id())
+        .raw("children", format!("[{}]", children.join(",")))
+        .render()
+}
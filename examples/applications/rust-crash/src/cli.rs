@@ -0,0 +1,263 @@
+//! Command-line flags, so a TSPM config can drive the fixture through `args:`
+//! as well as `env:`.
+//!
+//! Every knob is also a flag named after its variable (`--leak-rate 5` sets
+//! `LEAK_RATE`), given as `--flag value` or `--flag=value`. Flags take
+//! precedence over `ENV_FILE` and the environment.
+
+use std::process;
+
+use crate::config;
+
+/// `(flag, value hint, description)`; an empty hint marks a boolean flag.
+type Flag = (&'static str, &'static str, &'static str);
+
+const SECTIONS: &[(&str, &[Flag])] = &[
+    (
+        "Server",
+        &[
+            ("port", "N", "Base port; the instance ID is added to it (default 8080)"),
+            ("bind-addr", "ADDR", "Address to listen on (default 0.0.0.0)"),
+            ("instance-var", "NAME", "Variable holding the instance ID (default NODE_APP_INSTANCE)"),
+            ("port-strategy", "MODE", "offset, shared or ephemeral (default offset)"),
+            ("shared-port", "", "Same as --port-strategy shared"),
+            ("socket-path", "PATH", "Serve on this Unix socket instead; {instance} is substituted"),
+            ("port-file", "PATH", "Where an ephemeral port is written; {instance} is substituted"),
+            ("startup-delay-ms", "MS", "Delay before listening (default 200)"),
+            ("bind-retry", "N", "Retry an occupied port N times, or forever with true"),
+            ("bind-retry-ms", "MS", "First bind retry delay, doubling each time (default 100)"),
+            ("bind-retry-max-ms", "MS", "Longest bind retry delay (default 5000)"),
+            ("http-threads", "N", "Worker threads serving connections (default 64)"),
+            ("keep-alive-ms", "MS", "Idle keep-alive timeout (default 5000)"),
+            ("ready-notify", "MODE", "auto, stdout, socket or none (default auto)"),
+            ("ready-delay-ms", "MS", "Delay the readiness notification after binding"),
+            ("stdin-console", "BOOL", "Read commands from stdin (default true)"),
+            ("scenario", "PATH", "JSON timeline of console commands to run"),
+            ("env-file", "PATH", "Dotenv file overriding the environment, re-read on SIGHUP"),
+            ("env-redact", "WORDS", "Extra words whose variables /env redacts"),
+        ],
+    ),
+    (
+        "Logging",
+        &[
+            ("rust-log", "LEVEL", "off, error, warn, info, debug or trace (default info)"),
+            ("log-format", "FORMAT", "text or json (default text)"),
+        ],
+    ),
+    (
+        "Crashes and exits",
+        &[
+            ("enable-crash", "", "Allow /crash, /exit, /abort and /signal"),
+            ("exit-on-start", "CODE", "Exit with CODE before binding"),
+            ("crash-exit", "EXIT", "panic, abort, exit:N or signal:NAME (default panic)"),
+            ("crash-after-ms", "MS", "Crash this long after startup"),
+            ("crash-after-requests", "N", "Crash after answering the Nth request"),
+            ("crash-on-start-probability", "P", "Crash on startup with probability P"),
+            ("crash-seed", "N", "Seed for --crash-on-start-probability"),
+            ("crash-until-restart", "N", "Only crash until restarted N times"),
+            ("crash-counter-file", "PATH", "File counting the starts; {process} and {instance} are substituted"),
+            ("crash-counter-session", "ID", "Restart the count when this changes (default: parent PID)"),
+            ("restart-count", "N", "Restart count to use instead of the counter file"),
+        ],
+    ),
+    (
+        "Shutdown",
+        &[
+            ("shutdown-behavior", "MODE", "graceful, ignore or slow (default graceful)"),
+            ("drain-ms", "MS", "Graceful drain window (default 3000)"),
+            ("shutdown-slow-ms", "MS", "Stall of a slow shutdown (default 60000)"),
+        ],
+    ),
+    (
+        "Memory and CPU",
+        &[
+            ("leak-rate", "MB", "Background leak rate in MB per second"),
+            ("leak-mode", "MODE", "heap, mmap or touch (default heap)"),
+            ("leak-interval-ms", "MS", "Background leak interval (default 1000)"),
+            ("leak-limit-mb", "MB", "Stop leaking after this much"),
+            ("leak-reserve-mb", "MB", "Reservation size for touch mode (default 1024)"),
+            ("burn-threads", "N", "CPU burn threads at startup"),
+            ("burn-pct", "PCT", "Burn duty cycle (default 100)"),
+            ("burn-secs", "S", "Stop the startup burn after S seconds"),
+        ],
+    ),
+    (
+        "Log flood",
+        &[
+            ("log-flood-rate", "N", "Lines per second at startup"),
+            ("log-flood-shape", "SHAPE", "lines, long, interleaved, binary, utf8-split or mixed"),
+            ("log-flood-bytes", "N", "Bytes per line"),
+            ("log-flood-secs", "S", "Stop the startup flood after S seconds"),
+            ("log-flood-seed", "N", "Seed for the binary shape"),
+        ],
+    ),
+    (
+        "Health",
+        &[
+            ("health-status", "CODE", "Status of /health (default 200)"),
+            ("health-body", "TEXT", "Body of /health"),
+            ("health-latency-ms", "MS", "Delay before /health answers"),
+            ("health-require-header", "HEADER", "Header /health requires, as Name: value"),
+            ("ready-status", "CODE", "Status of /ready (default 200)"),
+            ("ready-body", "TEXT", "Body of /ready"),
+            ("ready-latency-ms", "MS", "Delay before /ready answers"),
+            ("ready-require-header", "HEADER", "Header /ready requires, as Name: value"),
+            ("health-script", "SPEC", "Repeating timeline, e.g. healthy:10s,unhealthy:3s"),
+            ("health-script-probe", "PROBE", "health, ready or all (default health)"),
+            ("health-slow-ms", "MS", "Latency of slow script steps (default 2000)"),
+            ("health-jitter-pct", "PCT", "Jitter applied to script steps"),
+            ("health-seed", "N", "Seed for the jitter"),
+            ("hang-mode", "MODE", "none, silent, stall-body or no-accept"),
+            ("hang-secs", "S", "Revert the startup hang mode after S seconds"),
+            ("watchdog-usec", "US", "Send a watchdog heartbeat every half of US microseconds"),
+            ("watchdog-stop-after-ms", "MS", "Stop the heartbeats this long after startup"),
+        ],
+    ),
+    (
+        "Process tree",
+        &[
+            ("spawn-children", "N", "Worker children started at boot"),
+            ("spawn-grandchildren", "N", "Grandchildren per child"),
+            ("child-isolation", "LIST", "none, group or session, round-robin"),
+            ("grandchild-isolation", "LIST", "Same, for grandchildren"),
+        ],
+    ),
+];
+
+fn find(name: &str) -> Option<&'static Flag> {
+    SECTIONS
+        .iter()
+        .flat_map(|(_, flags)| flags.iter())
+        .find(|(flag, _, _)| *flag == name)
+}
+
+/// `--leak-rate` -> `LEAK_RATE`.
+fn var_name(flag: &str) -> String {
+    flag.replace('-', "_").to_ascii_uppercase()
+}
+
+fn usage() -> String {
+    let mut out = String::from(
+        "Usage: rust-crash-app [--flag value | --flag=value]...\n\n\
+         Every flag mirrors the environment variable of the same name (--leak-rate\n\
+         sets LEAK_RATE) and takes precedence over it.\n",
+    );
+    for (section, flags) in SECTIONS {
+        out.push_str(&format!("\n{}:\n", section));
+        for (flag, hint, help) in flags.iter() {
+            let left = format!("--{} {}", flag, hint);
+            out.push_str(&format!("  {:<32} {}\n", left.trim_end(), help));
+        }
+    }
+    out.push_str(&format!("\n  {:<32} {}\n", "-h, --help", "Print this help"));
+    out
+}
+
+/// Turn `args` (without the program name) into variable overrides.
+fn parse(args: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut values = Vec::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            return Err(format!("unexpected argument '{}'", arg));
+        };
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        let Some((name, hint, _)) = find(name) else {
+            return Err(format!("unknown flag '--{}'", name));
+        };
+        let value = match inline {
+            Some(value) => value,
+            None if hint.is_empty() => match args.peek() {
+                Some(next) if config::is_truthy(next) || is_falsy(next) => args.next().cloned().unwrap_or_default(),
+                _ => "true".to_string(),
+            },
+            None => args
+                .next()
+                .cloned()
+                .ok_or_else(|| format!("flag '--{}' needs a value", name))?,
+        };
+        values.push((var_name(name), value));
+    }
+    Ok(values)
+}
+
+fn is_falsy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "false" | "0" | "no" | "off"
+    )
+}
+
+/// Apply the command line, printing usage and exiting for `--help` or a
+/// malformed flag.
+pub fn apply(args: &[String]) {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print!("{}", usage());
+        process::exit(0);
+    }
+    match parse(args) {
+        Ok(values) => config::set_flags(values),
+        Err(e) => {
+            eprintln!("rust-crash-app: {}\n\n{}", e, usage());
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Result<Vec<(String, String)>, String> {
+        parse(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn takes_values_separately_or_inline() {
+        assert_eq!(
+            run(&["--leak-rate", "5", "--port=9000", "--bind-addr=", "--health-script=healthy:10s,unhealthy:3s"]),
+            Ok(pairs(&[
+                ("LEAK_RATE", "5"),
+                ("PORT", "9000"),
+                ("BIND_ADDR", ""),
+                ("HEALTH_SCRIPT", "healthy:10s,unhealthy:3s"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn boolean_flags_only_consume_boolean_words() {
+        assert_eq!(
+            run(&["--enable-crash", "--port", "1"]),
+            Ok(pairs(&[("ENABLE_CRASH", "true"), ("PORT", "1")]))
+        );
+        assert_eq!(
+            run(&["--enable-crash", "off", "--shared-port", "yes"]),
+            Ok(pairs(&[("ENABLE_CRASH", "off"), ("SHARED_PORT", "yes")]))
+        );
+        assert_eq!(run(&["--shared-port"]), Ok(pairs(&[("SHARED_PORT", "true")])));
+        assert_eq!(run(&["--enable-crash=false"]), Ok(pairs(&[("ENABLE_CRASH", "false")])));
+        // A non-boolean word after a boolean flag is a stray argument.
+        assert_eq!(run(&["--enable-crash", "maybe"]), Err("unexpected argument 'maybe'".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_flags_and_missing_values() {
+        assert_eq!(run(&["--nope"]), Err("unknown flag '--nope'".to_string()));
+        assert_eq!(run(&["--nope=1"]), Err("unknown flag '--nope'".to_string()));
+        assert_eq!(run(&["--port"]), Err("flag '--port' needs a value".to_string()));
+        assert_eq!(run(&["-p", "1"]), Err("unexpected argument '-p'".to_string()));
+    }
+
+    #[test]
+    fn value_flags_take_the_next_word_verbatim() {
+        assert_eq!(run(&["--port", "--leak-rate"]), Ok(pairs(&[("PORT", "--leak-rate")])));
+    }
+}
@@ -0,0 +1,167 @@
+//! Environment-driven knobs for the fixture.
+//!
+//! Command-line flags take precedence over values from the dotenv-style file
+//! named by `ENV_FILE`, then the `env` of a `SCENARIO` file, then the process
+//! environment. The file is re-read on SIGHUP, so any knob that is looked up at
+//! use time (rather than once at startup) follows a reload.
+
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+static FLAGS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+static OVERLAY: RwLock<Vec<(String, String)>> = RwLock::new(Vec::new());
+static SCENARIO: OnceLock<Vec<(String, String)>> = OnceLock::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Install the values given on the command line. Only the first call counts.
+pub fn set_flags(values: Vec<(String, String)>) {
+    let _ = FLAGS.set(values);
+}
+
+/// Install the values a scenario file sets. Only the first call counts.
+pub fn set_scenario(values: Vec<(String, String)>) {
+    let _ = SCENARIO.set(values);
+}
+
+fn flag_value(name: &str) -> Option<String> {
+    FLAGS
+        .get()
+        .and_then(|f| f.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.clone()))
+}
+
+/// Read a knob, treating empty values as unset.
+pub fn var(name: &str) -> Option<String> {
+    let overlay = || {
+        OVERLAY
+            .read()
+            .ok()
+            .and_then(|o| o.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone()))
+    };
+    let scenario = || {
+        SCENARIO
+            .get()
+            .and_then(|s| s.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.clone()))
+    };
+    flag_value(name)
+        .or_else(overlay)
+        .or_else(scenario)
+        .or_else(|| env::var(name).ok())
+        .filter(|v| !v.trim().is_empty())
+}
+
+/// Read and parse a knob, falling back to `default` when unset or malformed.
+pub fn parse_or<T: FromStr>(name: &str, default: T) -> T {
+    var(name)
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read a boolean knob (`true`, `1`, `yes` or `on`).
+pub fn flag(name: &str) -> bool {
+    var(name).is_some_and(|v| is_truthy(&v))
+}
+
+pub fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "yes" | "on"
+    )
+}
+
+/// Parse `500ms`, `10s`, `2m` or a bare number of milliseconds.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, scale) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = value.strip_suffix('m') {
+        (n, 60_000.0)
+    } else {
+        (value, 1.0)
+    };
+    let ms: f64 = number.trim().parse().ok()?;
+    (ms >= 0.0).then(|| Duration::from_micros((ms * scale * 1000.0) as u64))
+}
+
+/// Path of the reloadable env file, if one is configured.
+pub fn env_file() -> Option<String> {
+    flag_value("ENV_FILE")
+        .or_else(|| env::var("ENV_FILE").ok())
+        .filter(|p| !p.trim().is_empty())
+}
+
+/// How many times the env file has been reloaded since startup.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Load `ENV_FILE` at startup. Returns the number of values read.
+pub fn load() -> Result<usize, String> {
+    let Some(path) = env_file() else {
+        return Ok(0);
+    };
+    let values = read_env_file(&path)?;
+    let count = values.len();
+    *OVERLAY.write().map_err(|_| "config overlay poisoned")? = values;
+    Ok(count)
+}
+
+/// Re-read `ENV_FILE` and bump the generation. On error the previous values
+/// stay in effect.
+pub fn reload() -> Result<(u64, usize), String> {
+    let path = env_file().ok_or("ENV_FILE is not set")?;
+    let values = read_env_file(&path)?;
+    let count = values.len();
+    *OVERLAY.write().map_err(|_| "config overlay poisoned")? = values;
+    Ok((GENERATION.fetch_add(1, Ordering::SeqCst) + 1, count))
+}
+
+/// Parse `KEY=VALUE` lines, skipping blanks and `#` comments. An optional
+/// `export ` prefix and surrounding quotes are stripped.
+fn read_env_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration(" 10s "), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for bad in ["", "s", "-1s", "10h", "ten", "NaN", "5 minutes"] {
+            assert_eq!(parse_duration(bad), None, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! A line-based command console on stdin, so chaos can be driven through
+//! TSPM's `POST /processes/:name/input` and the dashboard terminal instead of
+//! curl. Each command maps onto an endpoint and echoes its result to stdout:
+//!
+//! ```text
+//! status | crash [EXIT] | exit [N] | abort | signal NAME
+//! leak MB [MODE] | burn THREADS [PCT [SECS]] | logs RATE [SHAPE [SECS]]
+//! health|ready up|down|STATUS | hang [MODE [SECS]] | watchdog stop|resume [SECS]
+//! reload | get PATH | help
+//! ```
+//!
+//! Exits do not need `ENABLE_CRASH`: whoever can write to stdin already
+//! controls the process. `STDIN_CONSOLE=false` turns the console off.
+
+use std::io::{self, BufRead, Write};
+use std::thread;
+
+use crate::config;
+use crate::exits::{self, Exit};
+use crate::http::{Request, Response};
+use crate::sys;
+
+const HELP: &str = "\
+commands:
+  status                      same as GET /status
+  crash [EXIT]                panic, abort, exit:N or signal:NAME (default panic)
+  exit [N]                    exit with code N (default 1)
+  abort                       abort (SIGABRT)
+  signal NAME                 raise HUP, INT, QUIT, ABRT, KILL, USR1, SEGV or TERM
+  leak MB [MODE]              leak MB megabytes (heap, mmap or touch)
+  burn THREADS [PCT [SECS]]   burn CPU; burn 0 stops
+  logs RATE [SHAPE [SECS]]    flood logs; logs 0 stops
+  health up|down|STATUS       set the /health status
+  ready up|down|STATUS        set the /ready status
+  hang [MODE [SECS]]          none, silent, stall-body or no-accept
+  watchdog stop|resume [SECS] stop or resume watchdog heartbeats
+  reload                      re-read ENV_FILE, like SIGHUP
+  get PATH                    any other endpoint, e.g. get /children?spawn=2
+  help                        this list";
+
+/// What a command line asks for.
+enum Command {
+    /// Serve `GET target` as if it had arrived over HTTP.
+    Get(String),
+    Exit(Exit),
+    Reload,
+    Help,
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = words.split_first().ok_or_else(|| "empty command".to_string())?;
+    let arg = |i: usize| args.get(i).copied();
+    let required = |i: usize, what: &str| arg(i).ok_or_else(|| format!("{} needs {}", name, what));
+
+    let command = match name.to_ascii_lowercase().as_str() {
+        "status" => Command::Get("/status".to_string()),
+        "crash" => Command::Exit(exits::parse(arg(0).unwrap_or("panic"))?),
+        "exit" => Command::Exit(exits::parse(arg(0).unwrap_or("1"))?),
+        "abort" => Command::Exit(Exit::Abort),
+        "signal" => Command::Exit(exits::parse(&format!("signal:{}", required(0, "a signal name")?))?),
+        "leak" => Command::Get(with_params(
+            "/leak",
+            &[("mb", Some(required(0, "a size in MB")?)), ("mode", arg(1))],
+        )),
+        "burn" => Command::Get(with_params(
+            "/burn",
+            &[("threads", Some(required(0, "a thread count")?)), ("pct", arg(1)), ("secs", arg(2))],
+        )),
+        "logs" => Command::Get(with_params(
+            "/logs",
+            &[("rate", Some(required(0, "a rate")?)), ("shape", arg(1)), ("secs", arg(2))],
+        )),
+        probe @ ("health" | "ready") => match arg(0) {
+            None => Command::Get(format!("/admin/health?probe={}", probe)),
+            Some(state) => {
+                let status = match state.to_ascii_lowercase().as_str() {
+                    "up" => "200",
+                    "down" => "503",
+                    _ => state,
+                };
+                Command::Get(format!("/admin/health?probe={}&status={}", probe, status))
+            }
+        },
+        "hang" => Command::Get(with_params("/admin/hang", &[("mode", arg(0)), ("secs", arg(1))])),
+        "watchdog" => Command::Get(with_params("/admin/watchdog", &[("state", arg(0)), ("secs", arg(1))])),
+        "reload" => Command::Reload,
+        "get" => {
+            let path = required(0, "a path")?;
+            Command::Get(if path.starts_with('/') { path.to_string() } else { format!("/{}", path) })
+        }
+        "help" | "?" => Command::Help,
+        other => return Err(format!("unknown command '{}' (try help)", other)),
+    };
+    Ok(command)
+}
+
+/// Check that `line` is a valid command without running it.
+pub fn check(line: &str) -> Result<(), String> {
+    parse(line).map(|_| ())
+}
+
+/// `path` plus the `key=value` pairs that are present.
+fn with_params(path: &str, params: &[(&str, Option<&str>)]) -> String {
+    let query: Vec<String> = params
+        .iter()
+        .filter_map(|(key, value)| value.map(|v| format!("{}={}", key, v)))
+        .collect();
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query.join("&"))
+    }
+}
+
+/// Run one command line, serving endpoint commands through `route`, and
+/// return what to echo. `Err` carries a usage error.
+pub fn execute(line: &str, instance: u16, route: &impl Fn(&Request) -> Response) -> Result<String, String> {
+    match parse(line)? {
+        Command::Get(target) => {
+            let request = Request::parse(format!("GET {} HTTP/1.1\r\n\r\n", target).as_bytes());
+            let response = route(&request);
+            Ok(format!("{} {}", response.status(), response.body().trim_end()))
+        }
+        Command::Exit(exit) => {
+            exits::schedule(exit, instance);
+            Ok(exit.describe())
+        }
+        Command::Reload => {
+            // The accept loop picks this up exactly like an external SIGHUP.
+            sys::raise(sys::SIGHUP);
+            Ok("RELOAD requested".to_string())
+        }
+        Command::Help => Ok(HELP.to_string()),
+    }
+}
+
+/// Read commands from stdin on a background thread until it closes.
+pub fn start<F>(instance: u16, route: F)
+where
+    F: Fn(&Request) -> Response + Send + 'static,
+{
+    if !config::var("STDIN_CONSOLE").is_none_or(|v| config::is_truthy(&v)) {
+        return;
+    }
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    debug!("Console stopped reading stdin: {}", e);
+                    return;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match execute(&line, instance, &route) {
+                Ok(reply) => reply,
+                Err(e) => format!("error: {}", e),
+            };
+            let mut stdout = io::stdout().lock();
+            let _ = writeln!(stdout, "{}", reply).and_then(|_| stdout.flush());
+        }
+        debug!("Console reached the end of stdin");
+    });
+}
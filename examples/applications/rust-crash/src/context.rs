@@ -0,0 +1,131 @@
+//! `/env` and `/context`: what this process actually received from TSPM, so
+//! config tests can assert on the environment, argv and working directory a
+//! native process is started with.
+//!
+//! Values of variables whose names contain a redacted word (`SECRET`, `TOKEN`,
+//! `PASSWORD`, `KEY`, `CREDENTIAL`, plus any listed in `ENV_REDACT`) are
+//! replaced with `[REDACTED]`.
+
+use std::env;
+use std::fs;
+use std::os::unix::process::parent_id;
+use std::process;
+
+use crate::config;
+use crate::http::Response;
+use crate::json;
+use crate::sys;
+
+const REDACTED: &str = "[REDACTED]";
+const DEFAULT_REDACT: [&str; 5] = ["SECRET", "TOKEN", "PASSWORD", "KEY", "CREDENTIAL"];
+
+fn is_redacted(name: &str, extra: &[String]) -> bool {
+    let name = name.to_ascii_uppercase();
+    DEFAULT_REDACT.iter().any(|word| name.contains(word)) || extra.iter().any(|word| name.contains(word.as_str()))
+}
+
+/// The process environment as a JSON object, sorted by name.
+fn env_json() -> String {
+    let extra: Vec<String> = config::var("ENV_REDACT")
+        .unwrap_or_default()
+        .split(',')
+        .map(|w| w.trim().to_ascii_uppercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut vars: Vec<(String, String)> = env::vars_os()
+        .map(|(k, v)| (k.to_string_lossy().into_owned(), v.to_string_lossy().into_owned()))
+        .collect();
+    vars.sort();
+
+    vars.iter()
+        .fold(json::Object::new(), |object, (name, value)| {
+            let value = if is_redacted(name, &extra) { REDACTED } else { value };
+            object.str(name, value)
+        })
+        .render()
+}
+
+/// `GET /env`
+pub fn env() -> Response {
+    Response::json(200, env_json())
+}
+
+/// Parse `/proc/self/limits` into `{"maxOpenFiles": {"soft": 1024, ...}}`.
+/// `unlimited` is reported as a string; `null` without `/proc`.
+fn limits_json() -> String {
+    let Ok(text) = fs::read_to_string("/proc/self/limits") else {
+        return "null".to_string();
+    };
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    let (Some(soft), Some(hard), Some(units)) =
+        (header.find("Soft Limit"), header.find("Hard Limit"), header.find("Units"))
+    else {
+        return "null".to_string();
+    };
+
+    let value = |raw: &str| match raw.trim().parse::<u64>() {
+        Ok(n) => n.to_string(),
+        Err(_) => json::quote(raw.trim()),
+    };
+    lines
+        .filter(|line| line.len() > hard)
+        .fold(json::Object::new(), |object, line| {
+            let limit = json::Object::new()
+                .raw("soft", value(&line[soft..hard]))
+                .raw("hard", value(line.get(hard..units).unwrap_or(&line[hard..])))
+                .str("units", line.get(units..).unwrap_or_default().trim());
+            object.raw(&camel_case(&line[..soft]), limit.render())
+        })
+        .render()
+}
+
+/// `Max open files` -> `maxOpenFiles`.
+fn camel_case(words: &str) -> String {
+    words
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, word)| {
+            let word = word.to_ascii_lowercase();
+            match (i, word.chars().next()) {
+                (0, _) | (_, None) => word,
+                (_, Some(first)) => first.to_ascii_uppercase().to_string() + &word[first.len_utf8()..],
+            }
+        })
+        .collect()
+}
+
+/// `GET /context`
+pub fn context(instance: u16, port: Option<u16>, socket: Option<&str>) -> Response {
+    let argv: Vec<String> = env::args_os()
+        .map(|arg| json::quote(&arg.to_string_lossy()))
+        .collect();
+    let cwd = env::current_dir().ok().map(|p| p.display().to_string());
+    let (uid, euid) = sys::user_ids();
+    let (gid, egid) = sys::group_ids();
+    let var = |name: &str| env::var(name).ok();
+
+    let tspm = json::Object::new()
+        .opt_str("processName", var("TSPM_PROCESS_NAME").as_deref())
+        .opt_str("instanceId", var("TSPM_INSTANCE_ID").as_deref())
+        .opt_str("nodeAppInstance", var("NODE_APP_INSTANCE").as_deref())
+        .opt_str("port", var("PORT").as_deref());
+
+    let body = json::Object::new()
+        .num("pid", process::id())
+        .num("ppid", parent_id())
+        .num("instance", instance)
+        .opt_num("port", port)
+        .opt_str("socket", socket)
+        .raw("argv", format!("[{}]", argv.join(",")))
+        .opt_str("cwd", cwd.as_deref())
+        .opt_str("exe", env::current_exe().ok().map(|p| p.display().to_string()).as_deref())
+        .num("uid", uid)
+        .num("euid", euid)
+        .num("gid", gid)
+        .num("egid", egid)
+        .raw("tspm", tspm.render())
+        .raw("rlimits", limits_json());
+    Response::json(200, body.render())
+}
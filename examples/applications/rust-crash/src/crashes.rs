@@ -0,0 +1,162 @@
+//! Deterministic crash schedules, so restart limits and backoff can be asserted
+//! without racing a client against the restart loop.
+//!
+//! - `CRASH_AFTER_MS`: crash this long after startup.
+//! - `CRASH_AFTER_REQUESTS`: crash right after answering the Nth request.
+//! - `CRASH_ON_START_PROBABILITY`: crash on startup with this probability,
+//!   reproducible with `CRASH_SEED`.
+//! - `CRASH_UNTIL_RESTART`: only run the schedules above (or crash on startup
+//!   if none is set) until the process has been restarted N times.
+//!
+//! Restarts are counted in a file, since TSPM does not pass its restart count
+//! to the child. `RESTART_COUNT`, if set, is used instead. The file is keyed
+//! by `TSPM_PROCESS_NAME` and instance ID, and starts over whenever the
+//! session changes: `CRASH_COUNTER_SESSION` if set, otherwise the parent PID,
+//! so a new supervisor (or test run) never inherits an old count.
+
+use std::env;
+use std::fs;
+use std::os::unix::process::parent_id;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::exits::{self, Exit};
+use crate::http::Request;
+use crate::rng::Rng;
+
+/// Requests left before a `CRASH_AFTER_REQUESTS` crash; `0` when disarmed.
+static REQUESTS_LEFT: AtomicU64 = AtomicU64::new(0);
+static REQUEST_EXIT: OnceLock<Exit> = OnceLock::new();
+
+/// `CRASH_COUNTER_FILE` with `{process}` and `{instance}` substituted, or a
+/// per-instance file in the temp directory.
+fn counter_file(instance: u16) -> PathBuf {
+    let process = config::var("TSPM_PROCESS_NAME").unwrap_or_else(|| "rust-crash-app".to_string());
+    // Keep the process name usable as part of a file name.
+    let process: String = process
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    config::var("CRASH_COUNTER_FILE")
+        .map(|p| PathBuf::from(p.replace("{process}", &process).replace("{instance}", &instance.to_string())))
+        .unwrap_or_else(|| env::temp_dir().join(format!("rust-crash-app-{}-{}.runs", process, instance)))
+}
+
+/// Identifies one supervisor session; the count restarts when it changes.
+fn session() -> String {
+    config::var("CRASH_COUNTER_SESSION").unwrap_or_else(|| format!("ppid:{}", parent_id()))
+}
+
+/// How many times this instance has been started before in this session,
+/// bumping the counter file on the way.
+fn previous_runs(instance: u16) -> u64 {
+    if let Some(count) = config::var("RESTART_COUNT").and_then(|c| c.trim().parse().ok()) {
+        return count;
+    }
+
+    let path = counter_file(instance);
+    let session = session();
+    // The file holds `<session> <runs>`; anything else starts over.
+    let runs = fs::read_to_string(&path)
+        .ok()
+        .and_then(|c| {
+            let (stored, runs) = c.trim().rsplit_once(' ')?;
+            (stored == session).then(|| runs.parse().ok()).flatten()
+        })
+        .unwrap_or(0);
+    if runs == 0 {
+        debug!("Starting crash counter {} for session {}", path.display(), session);
+    }
+    // Write then rename, so a crash mid-write never leaves a torn count.
+    let partial = path.with_extension("runs.tmp");
+    let written = fs::write(&partial, format!("{} {}\n", session, runs + 1)).and_then(|_| fs::rename(&partial, &path));
+    if let Err(e) = written {
+        warn!("Cannot update crash counter {}: {}", path.display(), e);
+    }
+    runs
+}
+
+fn crash(reason: &str, exit: Exit, instance: u16) -> ! {
+    warn!("💥 {} on instance {}: {}", reason, instance, exit.describe());
+    exits::terminate(exit)
+}
+
+/// Arm the schedules configured for this run. May not return.
+pub fn start(instance: u16) {
+    let exit = match config::var("CRASH_EXIT").map(|s| exits::parse(&s)) {
+        None => Exit::Panic,
+        Some(Ok(exit)) => exit,
+        Some(Err(e)) => {
+            error!("Invalid CRASH_EXIT: {}", e);
+            Exit::Panic
+        }
+    };
+    let until: Option<u64> = config::var("CRASH_UNTIL_RESTART").and_then(|n| n.trim().parse().ok());
+    let probability: Option<f64> = config::var("CRASH_ON_START_PROBABILITY").and_then(|p| p.trim().parse().ok());
+    let after_ms: Option<u64> = config::var("CRASH_AFTER_MS").and_then(|ms| ms.trim().parse().ok());
+    let after_requests: u64 = config::parse_or("CRASH_AFTER_REQUESTS", 0);
+
+    let runs = if until.is_some() || probability.is_some() {
+        previous_runs(instance)
+    } else {
+        0
+    };
+
+    if let Some(until) = until {
+        if runs >= until {
+            info!("Crash schedule disarmed after {} restart(s) (CRASH_UNTIL_RESTART={})", runs, until);
+            return;
+        }
+        info!("Crash schedule armed for run {} of {} (CRASH_UNTIL_RESTART)", runs + 1, until);
+    }
+
+    if let Some(probability) = probability {
+        // Run N uses the Nth draw of the seeded sequence, so a series of
+        // restarts replays identically.
+        let mut rng = config::var("CRASH_SEED")
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .map(Rng::new)
+            .unwrap_or_else(Rng::from_entropy);
+        for _ in 0..runs {
+            rng.next_u64();
+        }
+        if rng.next_f64() < probability {
+            crash("Crash on start (CRASH_ON_START_PROBABILITY)", exit, instance);
+        }
+    }
+
+    if let Some(ms) = after_ms {
+        info!("Crashing in {}ms (CRASH_AFTER_MS)", ms);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(ms));
+            crash("Crash after timeout (CRASH_AFTER_MS)", exit, instance);
+        });
+    }
+
+    if after_requests > 0 {
+        let _ = REQUEST_EXIT.set(exit);
+        REQUESTS_LEFT.store(after_requests, Ordering::SeqCst);
+        info!("Crashing after {} request(s) (CRASH_AFTER_REQUESTS)", after_requests);
+    }
+
+    if until.is_some() && probability.is_none() && after_ms.is_none() && after_requests == 0 {
+        crash("Crash on start (CRASH_UNTIL_RESTART)", exit, instance);
+    }
+}
+
+/// Count a served request towards `CRASH_AFTER_REQUESTS`. Probes and
+/// `/admin/*` calls are not counted, so health checks do not shift the crash.
+pub fn count_request(request: &Request, instance: u16) {
+    if matches!(request.path.as_str(), "/health" | "/ready") || request.path.starts_with("/admin") {
+        return;
+    }
+    let counted = REQUESTS_LEFT.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1));
+    if counted == Ok(1) {
+        let exit = REQUEST_EXIT.get().copied().unwrap_or(Exit::Panic);
+        crash("Crash after requests (CRASH_AFTER_REQUESTS)", exit, instance);
+    }
+}
@@ -0,0 +1,163 @@
+//! Every way the fixture can die, so `ManagedProcess.handleExit` can be tested
+//! against the exit code / signal combinations real Rust services produce.
+//!
+//! Termination always happens on a separate thread shortly after the response
+//! has been written, so the client still gets a reply.
+
+use std::os::raw::c_int;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::sys;
+
+/// Grace period between writing the response and terminating.
+const EXIT_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exit {
+    /// `panic!` — exit code 101, or SIGABRT when built with `panic = "abort"`.
+    Panic,
+    /// `process::exit(code)`.
+    Code(i32),
+    /// `process::abort()` — SIGABRT.
+    Abort,
+    /// Raise `sig` with its default disposition restored.
+    Signal(c_int),
+}
+
+impl Exit {
+    pub fn describe(self) -> String {
+        match self {
+            Exit::Panic => "CRASH".to_string(),
+            Exit::Code(code) => format!("EXIT {}", code),
+            Exit::Abort => "ABORT".to_string(),
+            Exit::Signal(sig) => format!("SIGNAL {}", sys::signal_name(sig)),
+        }
+    }
+}
+
+/// Parse `panic`, `abort`, `exit:N` (or a bare `N`) or `signal:SEGV`.
+pub fn parse(spec: &str) -> Result<Exit, String> {
+    let spec = spec.trim();
+    let invalid = || format!("unknown exit '{}' (expected panic, abort, exit:N or signal:NAME)", spec);
+    match spec.split_once(':') {
+        None if spec.eq_ignore_ascii_case("panic") => Ok(Exit::Panic),
+        None if spec.eq_ignore_ascii_case("abort") => Ok(Exit::Abort),
+        None => spec.parse::<u8>().map(|code| Exit::Code(i32::from(code))).map_err(|_| invalid()),
+        Some((kind, value)) if kind.eq_ignore_ascii_case("exit") => {
+            value.trim().parse::<u8>().map(|code| Exit::Code(i32::from(code))).map_err(|_| invalid())
+        }
+        Some((kind, value)) if kind.eq_ignore_ascii_case("signal") => {
+            sys::signal_from_name(value).map(Exit::Signal).ok_or_else(invalid)
+        }
+        Some(_) => Err(invalid()),
+    }
+}
+
+/// Terminate the process the requested way. Never returns.
+pub fn terminate(exit: Exit) -> ! {
+    match exit {
+        Exit::Panic => panic!("Intentional crash triggered via /crash endpoint!"),
+        Exit::Code(code) => process::exit(code),
+        Exit::Abort => process::abort(),
+        Exit::Signal(sig) => {
+            // Signals we normally capture must hit their default action so
+            // TSPM sees a real `signalCode` instead of a graceful exit.
+            sys::reset_signal_handler(sig);
+            sys::raise(sig);
+            // Only reachable if the signal was blocked or ignored.
+            process::exit(128 + sig);
+        }
+    }
+}
+
+/// Run `terminate(exit)` once the current response has gone out.
+pub fn schedule(exit: Exit, instance: u16) {
+    warn!(
+        "⚠️  Received {} command for instance {}!",
+        exit.describe(),
+        instance
+    );
+    thread::spawn(move || {
+        thread::sleep(EXIT_DELAY);
+        terminate(exit);
+    });
+}
+
+/// Honour `EXIT_ON_START=N` before the server binds.
+pub fn exit_on_start() {
+    if let Some(code) = config::var("EXIT_ON_START") {
+        match code.trim().parse::<u8>() {
+            Ok(code) => {
+                info!("Exiting on start with code {} (EXIT_ON_START)", code);
+                process::exit(i32::from(code));
+            }
+            Err(_) => warn!("Ignoring EXIT_ON_START '{}' (expected an exit code between 0 and 255)", code),
+        }
+    }
+}
+
+/// Route the exit endpoints. `None` means the path is not one of ours.
+pub fn handle(request: &Request, instance: u16) -> Option<Response> {
+    let exit = match request.path.as_str() {
+        "/exit" => match request.query("code").map(str::parse::<u8>) {
+            None => Exit::Code(1),
+            Some(Ok(code)) => Exit::Code(i32::from(code)),
+            Some(Err(_)) => return Some(Response::bad_request("code must be between 0 and 255")),
+        },
+        "/abort" => Exit::Abort,
+        "/signal" => match request.query("sig").map(sys::signal_from_name) {
+            Some(Some(sig)) => Exit::Signal(sig),
+            _ => {
+                let names: Vec<&str> = sys::signal_names().collect();
+                return Some(Response::bad_request(format!("sig must be one of {}", names.join(", "))));
+            }
+        },
+        _ => return None,
+    };
+
+    if !config::flag("ENABLE_CRASH") {
+        return Some(Response::text(
+            403,
+            "Exit endpoints are disabled (set ENABLE_CRASH=true)",
+        ));
+    }
+
+    schedule(exit, instance);
+    Some(Response::ok(format!(
+        "Instance {} terminating: {}",
+        instance,
+        exit.describe()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exit_specs() {
+        assert_eq!(parse("panic"), Ok(Exit::Panic));
+        assert_eq!(parse(" PANIC "), Ok(Exit::Panic));
+        assert_eq!(parse("abort"), Ok(Exit::Abort));
+        assert_eq!(parse("3"), Ok(Exit::Code(3)));
+        assert_eq!(parse("exit:255"), Ok(Exit::Code(255)));
+        assert_eq!(parse("Exit: 0"), Ok(Exit::Code(0)));
+        assert_eq!(parse("signal:SEGV"), Ok(Exit::Signal(sys::SIGSEGV)));
+        assert_eq!(parse("signal:sigterm"), Ok(Exit::Signal(sys::SIGTERM)));
+        assert_eq!(parse("signal:usr1"), Ok(Exit::Signal(sys::SIGUSR1)));
+    }
+
+    #[test]
+    fn rejects_unknown_exit_specs() {
+        for bad in ["", "256", "-1", "exit:", "exit:256", "signal:PIPE", "kill:9", "crash"] {
+            assert_eq!(
+                parse(bad),
+                Err(format!("unknown exit '{}' (expected panic, abort, exit:N or signal:NAME)", bad)),
+            );
+        }
+    }
+}
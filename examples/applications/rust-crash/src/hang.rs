@@ -0,0 +1,171 @@
+//! Hang and half-open modes for checking that health-check timeouts and
+//! `retries` actually fire. Selected by `HANG_MODE` or at runtime via
+//! `/admin/hang`:
+//! - `silent`: accept and read the request, then never answer
+//! - `stall-body`: send headers and part of the body, then stall
+//! - `no-accept`: stop calling `accept()` so the listen backlog fills up
+//!
+//! `/admin/*` requests are never hung, so the mode can be switched back unless
+//! it is `no-accept`; use `secs=` to auto-revert, or send SIGUSR1. Hung
+//! connections are held on their own threads rather than HTTP workers, and
+//! let go as soon as the client disconnects.
+
+use std::io::{ErrorKind, Read, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::listener::Stream;
+
+const HOLD_POLL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    None,
+    Silent,
+    StallBody,
+    NoAccept,
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::None => "none",
+            Mode::Silent => "silent",
+            Mode::StallBody => "stall-body",
+            Mode::NoAccept => "no-accept",
+        }
+    }
+
+    fn from_u8(value: u8) -> Mode {
+        match value {
+            1 => Mode::Silent,
+            2 => Mode::StallBody,
+            3 => Mode::NoAccept,
+            _ => Mode::None,
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Mode::None),
+            "silent" => Ok(Mode::Silent),
+            "stall-body" | "stall" => Ok(Mode::StallBody),
+            "no-accept" => Ok(Mode::NoAccept),
+            other => Err(format!(
+                "unknown hang mode '{}' (expected none, silent, stall-body or no-accept)",
+                other
+            )),
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(Mode::None as u8);
+/// Bumped on every switch so a stale auto-revert timer does nothing.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn mode() -> Mode {
+    Mode::from_u8(MODE.load(Ordering::SeqCst))
+}
+
+/// Switch modes, reverting to `none` after `revert_after` if given.
+pub fn set(mode: Mode, revert_after: Option<Duration>) {
+    MODE.store(mode as u8, Ordering::SeqCst);
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    match revert_after {
+        Some(after) => {
+            warn!("⏸️  Hang mode {} for {}ms", mode.name(), after.as_millis());
+            thread::spawn(move || {
+                thread::sleep(after);
+                if GENERATION.load(Ordering::SeqCst) == generation {
+                    MODE.store(Mode::None as u8, Ordering::SeqCst);
+                    info!("▶️  Hang mode {} expired", mode.name());
+                }
+            });
+        }
+        None if mode == Mode::None => info!("▶️  Hang mode off"),
+        None => warn!("⏸️  Hang mode {}", mode.name()),
+    }
+}
+
+pub fn start_from_env() {
+    let mode = config::parse_or("HANG_MODE", Mode::None);
+    if mode != Mode::None {
+        set(
+            mode,
+            config::var("HANG_SECS").and_then(|s| s.trim().parse().ok()).map(Duration::from_secs),
+        );
+    }
+}
+
+/// The mode `request` should be hung in, if any.
+pub fn intercept(request: &Request) -> Option<Mode> {
+    if request.path.starts_with("/admin") {
+        return None;
+    }
+    match mode() {
+        mode @ (Mode::Silent | Mode::StallBody) => Some(mode),
+        Mode::None | Mode::NoAccept => None,
+    }
+}
+
+/// Hang `stream` on a thread of its own, so hung probes never use up the
+/// HTTP workers that `/admin/hang` needs. `guards` (connection and in-flight
+/// tracking) are released when the connection is.
+pub fn hold(mode: Mode, mut stream: Stream, guards: impl Send + 'static) {
+    thread::spawn(move || {
+        let _guards = guards;
+        if mode == Mode::StallBody {
+            let body = "Hello from a stalled instance";
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                &body[..body.len() / 2]
+            );
+            if stream.write_all(head.as_bytes()).and_then(|_| stream.flush()).is_err() {
+                return;
+            }
+        }
+        if let Err(e) = stream.set_read_timeout(HOLD_POLL) {
+            debug!("Dropping hung connection: {}", e);
+            return;
+        }
+
+        // Keep the connection open until the mode changes or the client
+        // gives up; anything it sends meanwhile is discarded.
+        let mut buf = [0; 1024];
+        while self::mode() == mode {
+            match stream.read(&mut buf) {
+                Ok(0) => {
+                    debug!("Client closed a hung connection");
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+                Err(e) => {
+                    debug!("Dropping hung connection: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// `GET /admin/hang?mode=none|silent|stall-body|no-accept&secs=N`
+pub fn admin(request: &Request) -> Result<Response, Response> {
+    let Some(mode) = request.parse_query::<Mode>("mode")? else {
+        return Ok(Response::ok(format!("Hang mode: {}", mode().name())));
+    };
+    let secs = request.parse_query("secs")?.map(Duration::from_secs);
+
+    set(mode, secs);
+    Ok(Response::ok(format!("Hang mode: {}", mode.name())))
+}
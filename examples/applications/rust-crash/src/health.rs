@@ -0,0 +1,423 @@
+//! `/health` and `/ready` probes whose answers can be flipped at runtime via
+//! `/admin/health`, for exercising TSPM's HTTP health-check strategy.
+//!
+//! Each probe has a status code, body, latency and an optional required
+//! header, seeded from `HEALTH_*` / `READY_*` environment variables.
+//!
+//! A health script (`HEALTH_SCRIPT=healthy:10s,unhealthy:3s,slow:5s`) walks the
+//! probes through a repeating timeline instead. Step durations can be jittered
+//! by `HEALTH_JITTER_PCT`, drawn from `HEALTH_SEED` so runs are reproducible.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::json;
+use crate::rng::Rng;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Health,
+    Ready,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Health => "health",
+            Kind::Ready => "ready",
+        }
+    }
+
+    fn env_prefix(self) -> &'static str {
+        match self {
+            Kind::Health => "HEALTH",
+            Kind::Ready => "READY",
+        }
+    }
+
+    fn default_body(self) -> &'static str {
+        match self {
+            Kind::Health => "OK",
+            Kind::Ready => "READY",
+        }
+    }
+}
+
+/// A header the prober must send; `None` value means any value is accepted.
+#[derive(Clone, Debug)]
+struct RequiredHeader {
+    name: String,
+    value: Option<String>,
+}
+
+impl RequiredHeader {
+    /// Parse `Name: value` or a bare `Name`. Empty input clears the requirement.
+    fn parse(spec: &str) -> Option<RequiredHeader> {
+        let (name, value) = match spec.split_once(':') {
+            Some((n, v)) => (n.trim(), Some(v.trim().to_string())),
+            None => (spec.trim(), None),
+        };
+        (!name.is_empty()).then(|| RequiredHeader {
+            name: name.to_string(),
+            value: value.filter(|v| !v.is_empty()),
+        })
+    }
+
+    fn describe(&self) -> String {
+        match &self.value {
+            Some(v) => format!("{}: {}", self.name, v),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Probe {
+    status: u16,
+    body: String,
+    latency_ms: u64,
+    require_header: Option<RequiredHeader>,
+}
+
+impl Probe {
+    fn from_env(kind: Kind) -> Probe {
+        let prefix = kind.env_prefix();
+        Probe {
+            status: config::parse_or(&format!("{}_STATUS", prefix), 200),
+            body: config::var(&format!("{}_BODY", prefix))
+                .unwrap_or_else(|| kind.default_body().to_string()),
+            latency_ms: config::parse_or(&format!("{}_LATENCY_MS", prefix), 0),
+            require_header: config::var(&format!("{}_REQUIRE_HEADER", prefix))
+                .and_then(|spec| RequiredHeader::parse(&spec)),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let header = self.require_header.as_ref().map(RequiredHeader::describe);
+        json::Object::new()
+            .num("status", self.status)
+            .str("body", &self.body)
+            .num("latencyMs", self.latency_ms)
+            .opt_str("requireHeader", header.as_deref())
+            .render()
+    }
+}
+
+struct Probes {
+    health: Probe,
+    ready: Probe,
+}
+
+impl Probes {
+    fn get_mut(&mut self, kind: Kind) -> &mut Probe {
+        match kind {
+            Kind::Health => &mut self.health,
+            Kind::Ready => &mut self.ready,
+        }
+    }
+}
+
+static PROBES: OnceLock<Mutex<Probes>> = OnceLock::new();
+
+fn probes() -> MutexGuard<'static, Probes> {
+    PROBES
+        .get_or_init(|| {
+            Mutex::new(Probes {
+                health: Probe::from_env(Kind::Health),
+                ready: Probe::from_env(Kind::Ready),
+            })
+        })
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Answer `/health` or `/ready` with the probe's current settings.
+pub fn serve(kind: Kind, request: &Request) -> Response {
+    let probe = probes().get_mut(kind).clone();
+
+    if probe.latency_ms > 0 {
+        thread::sleep(Duration::from_millis(probe.latency_ms));
+    }
+
+    if let Some(required) = &probe.require_header {
+        let ok = match (request.header(&required.name), &required.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if !ok {
+            return Response::text(401, format!("Missing required header {}", required.describe()));
+        }
+    }
+
+    Response::text(probe.status, probe.body)
+}
+
+/// `GET /admin/health?probe=health|ready|all&status=&body=&latency_ms=&header=&reset=true`
+pub fn admin(request: &Request) -> Result<Response, Response> {
+    let Some(kinds) = parse_kinds(request.query("probe").unwrap_or("all")) else {
+        return Err(Response::bad_request("probe must be health, ready or all"));
+    };
+
+    let status = request.parse_query::<u16>("status")?;
+    if status.is_some_and(|s| !(100..=599).contains(&s)) {
+        return Err(Response::bad_request("status must be between 100 and 599"));
+    }
+    let latency_ms = request.parse_query::<u64>("latency_ms")?;
+    let body = request.query_decoded("body");
+    let header = request.query_decoded("header");
+    let reset = request.query("reset").is_some_and(config::is_truthy);
+
+    let mut probes = probes();
+    for &kind in kinds {
+        let probe = probes.get_mut(kind);
+        if reset {
+            *probe = Probe::from_env(kind);
+        }
+        if let Some(status) = status {
+            probe.status = status;
+        }
+        if let Some(body) = &body {
+            probe.body = body.clone();
+        }
+        if let Some(ms) = latency_ms {
+            probe.latency_ms = ms;
+        }
+        if let Some(spec) = &header {
+            probe.require_header = RequiredHeader::parse(spec);
+        }
+        info!("🩺 {} probe now {}", kind.name(), probe.to_json());
+    }
+
+    Ok(Response::json(200, state_json(&probes)))
+}
+
+fn state_json(probes: &Probes) -> String {
+    json::Object::new()
+        .raw("health", probes.health.to_json())
+        .raw("ready", probes.ready.to_json())
+        .render()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StepState {
+    Healthy,
+    Unhealthy,
+    /// Healthy status, answered after `HEALTH_SLOW_MS`.
+    Slow,
+    Status(u16),
+}
+
+impl StepState {
+    fn describe(self) -> String {
+        match self {
+            StepState::Healthy => "healthy".to_string(),
+            StepState::Unhealthy => "unhealthy".to_string(),
+            StepState::Slow => "slow".to_string(),
+            StepState::Status(code) => code.to_string(),
+        }
+    }
+}
+
+impl FromStr for StepState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "healthy" | "up" => Ok(StepState::Healthy),
+            "unhealthy" | "down" => Ok(StepState::Unhealthy),
+            "slow" => Ok(StepState::Slow),
+            other => match other.parse::<u16>() {
+                Ok(code) if (100..=599).contains(&code) => Ok(StepState::Status(code)),
+                _ => Err(format!(
+                    "unknown health state '{}' (expected healthy, unhealthy, slow or a status code)",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    state: StepState,
+    duration: Duration,
+}
+
+/// Parse `state:duration[,state:duration...]`.
+fn parse_script(spec: &str) -> Result<Vec<Step>, String> {
+    let steps = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|step| {
+            let (state, duration) = step
+                .split_once(':')
+                .ok_or_else(|| format!("step '{}' must be state:duration", step))?;
+            Ok(Step {
+                state: state.parse()?,
+                duration: config::parse_duration(duration)
+                    .ok_or_else(|| format!("invalid duration '{}'", duration))?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if steps.iter().all(|s| s.duration.is_zero()) {
+        return Err("health script needs at least one step with a non-zero duration".to_string());
+    }
+    Ok(steps)
+}
+
+/// Bumped whenever a script starts or stops; old script threads exit.
+static SCRIPT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Run `steps` on `kinds` in a loop until another script replaces it.
+fn start_script(steps: Vec<Step>, kinds: &'static [Kind], seed: Option<u64>, jitter_pct: u8) {
+    let generation = SCRIPT_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let slow_ms: u64 = config::parse_or("HEALTH_SLOW_MS", 2000);
+    let mut rng = seed.map(Rng::new).unwrap_or_else(Rng::from_entropy);
+    let jitter = f64::from(jitter_pct.min(100)) / 100.0;
+
+    info!(
+        "🩺 Health script started: {} step(s), jitter {}%, seed {}",
+        steps.len(),
+        jitter_pct,
+        seed.map_or("random".to_string(), |s| s.to_string())
+    );
+
+    thread::spawn(move || {
+        for (index, step) in steps.iter().enumerate().cycle() {
+            if SCRIPT_GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+
+            let factor = 1.0 + jitter * (rng.next_f64() * 2.0 - 1.0);
+            let duration = step.duration.mul_f64(factor);
+            {
+                let mut probes = probes();
+                for &kind in kinds {
+                    let probe = probes.get_mut(kind);
+                    let (status, latency_ms) = match step.state {
+                        StepState::Healthy => (200, 0),
+                        StepState::Unhealthy => (503, 0),
+                        StepState::Slow => (200, slow_ms),
+                        StepState::Status(code) => (code, 0),
+                    };
+                    probe.status = status;
+                    probe.latency_ms = latency_ms;
+                }
+            }
+            info!(
+                "🩺 Health script step {}/{}: {} for {}ms",
+                index + 1,
+                steps.len(),
+                step.state.describe(),
+                duration.as_millis()
+            );
+            thread::sleep(duration);
+        }
+    });
+}
+
+fn stop_script() {
+    SCRIPT_GENERATION.fetch_add(1, Ordering::SeqCst);
+    info!("🩺 Health script stopped");
+}
+
+/// `health`, `ready` or `all` to the probes it selects.
+fn parse_kinds(probe: &str) -> Option<&'static [Kind]> {
+    match probe {
+        "health" => Some(&[Kind::Health]),
+        "ready" => Some(&[Kind::Ready]),
+        "all" => Some(&[Kind::Health, Kind::Ready]),
+        _ => None,
+    }
+}
+
+/// Start the script configured by `HEALTH_SCRIPT` and friends.
+pub fn start_script_from_env() {
+    let Some(spec) = config::var("HEALTH_SCRIPT") else {
+        return;
+    };
+    let probe = config::var("HEALTH_SCRIPT_PROBE").unwrap_or_else(|| "health".to_string());
+    let Some(kinds) = parse_kinds(probe.trim()) else {
+        error!("Invalid HEALTH_SCRIPT_PROBE '{}'", probe);
+        return;
+    };
+    match parse_script(&spec) {
+        Ok(steps) => start_script(
+            steps,
+            kinds,
+            config::var("HEALTH_SEED").and_then(|s| s.trim().parse().ok()),
+            config::parse_or("HEALTH_JITTER_PCT", 0),
+        ),
+        Err(e) => error!("Invalid HEALTH_SCRIPT: {}", e),
+    }
+}
+
+/// `GET /admin/health/script?spec=healthy:10s,unhealthy:3s&probe=&seed=&jitter=`
+///
+/// An empty `spec` stops the running script and leaves the probes as they are.
+pub fn admin_script(request: &Request) -> Result<Response, Response> {
+    let Some(spec) = request.query_decoded("spec") else {
+        return Err(Response::bad_request("spec is required (empty to stop)"));
+    };
+    if spec.trim().is_empty() {
+        stop_script();
+        return Ok(Response::ok("Health script stopped"));
+    }
+
+    let Some(kinds) = parse_kinds(request.query("probe").unwrap_or("health")) else {
+        return Err(Response::bad_request("probe must be health, ready or all"));
+    };
+    let seed = request.parse_query("seed")?;
+    let jitter = request.parse_query::<u8>("jitter")?.unwrap_or(0);
+    if jitter > 100 {
+        return Err(Response::bad_request("jitter must be between 0 and 100"));
+    }
+
+    let steps = parse_script(&spec).map_err(Response::bad_request)?;
+    let count = steps.len();
+    start_script(steps, kinds, seed, jitter);
+    Ok(Response::ok(format!("Health script started with {} step(s)", count)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_script_steps() {
+        let steps = parse_script("healthy:10s, unhealthy:3s,slow:500ms,418:1m,").unwrap();
+        let parsed: Vec<(StepState, Duration)> = steps.iter().map(|s| (s.state, s.duration)).collect();
+        assert_eq!(
+            parsed,
+            [
+                (StepState::Healthy, Duration::from_secs(10)),
+                (StepState::Unhealthy, Duration::from_secs(3)),
+                (StepState::Slow, Duration::from_millis(500)),
+                (StepState::Status(418), Duration::from_secs(60)),
+            ]
+        );
+        assert_eq!(parse_script("up:0,down:1s").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_scripts() {
+        assert_eq!(parse_script("healthy").unwrap_err(), "step 'healthy' must be state:duration");
+        assert_eq!(parse_script("healthy:soon").unwrap_err(), "invalid duration 'soon'");
+        assert!(parse_script("sick:1s").unwrap_err().starts_with("unknown health state 'sick'"));
+        assert!(parse_script("600:1s").is_err());
+        for empty in ["", " , ", "healthy:0,unhealthy:0ms"] {
+            assert_eq!(
+                parse_script(empty).unwrap_err(),
+                "health script needs at least one step with a non-zero duration"
+            );
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Just enough HTTP/1.1 to route fixture endpoints: request heads, bodies
+//! framed by `Content-Length`, and keep-alive.
+
+use std::fmt::Display;
+use std::io::{self, BufRead, Read, Write};
+use std::str::FromStr;
+
+/// Upper bound on the request line plus headers.
+const MAX_HEAD: usize = 16 * 1024;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    version: String,
+    query: String,
+    headers: Vec<(String, String)>,
+}
+
+/// Read the next request off a connection, discarding its body. Returns
+/// `Ok(None)` when the peer closes or goes idle between requests, and an
+/// `InvalidData` error for requests that should be answered with a 400.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut head = Vec::new();
+    loop {
+        let mut line = Vec::new();
+        let read = match reader.by_ref().take((MAX_HEAD + 1 - head.len()) as u64).read_until(b'\n', &mut line) {
+            Ok(read) => read,
+            Err(e) if head.is_empty() && is_idle(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if read == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid-request"));
+        }
+        if head.len() + line.len() > MAX_HEAD {
+            return Err(invalid("request head too large"));
+        }
+        let blank = line == b"\r\n" || line == b"\n";
+        // Tolerate stray blank lines before the request line.
+        if blank && head.is_empty() {
+            continue;
+        }
+        head.extend_from_slice(&line);
+        if blank {
+            break;
+        }
+    }
+
+    let request = Request::parse(&head);
+    if request.method.is_empty() || !request.version.starts_with("HTTP/1.") {
+        return Err(invalid("malformed request line"));
+    }
+    if request.header("transfer-encoding").is_some() {
+        return Err(invalid("chunked request bodies are not supported"));
+    }
+    let length = match request.header("content-length").map(str::parse::<u64>) {
+        None => 0,
+        Some(Ok(length)) => length,
+        Some(Err(_)) => return Err(invalid("invalid Content-Length")),
+    };
+    io::copy(&mut reader.take(length), &mut io::sink())?;
+    Ok(Some(request))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Read timeouts surface as `WouldBlock` on Unix and `TimedOut` elsewhere.
+fn is_idle(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+impl Request {
+    /// Parse the request line (`GET /leak?mb=5 HTTP/1.1`) and headers out of
+    /// a raw buffer.
+    pub fn parse(raw: &[u8]) -> Request {
+        let text = String::from_utf8_lossy(raw);
+        let mut lines = text.lines();
+        let mut parts = lines.next().unwrap_or_default().split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or("/");
+        let version = parts.next().unwrap_or_default().to_string();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Request {
+            method,
+            path: path.to_string(),
+            version,
+            query: query.to_string(),
+            headers,
+        }
+    }
+
+    /// Value of a header, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the client wants the connection kept open after the response.
+    pub fn keep_alive(&self) -> bool {
+        match self.header("connection").map(str::to_ascii_lowercase) {
+            Some(c) if c.contains("close") => false,
+            Some(c) if c.contains("keep-alive") => true,
+            _ => self.version == "HTTP/1.1",
+        }
+    }
+
+    /// First value of a query-string parameter.
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Optional query-string parameter parsed as `T`. A value that does not
+    /// parse comes back as the 400 to answer with.
+    pub fn parse_query<T>(&self, key: &str) -> Result<Option<T>, Response>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.query(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| Response::bad_request(format!("invalid {}: {}", key, e)))
+            })
+            .transpose()
+    }
+
+    /// Query-string parameter with `+` and `%XX` escapes decoded.
+    pub fn query_decoded(&self, key: &str) -> Option<String> {
+        self.query(key).map(percent_decode)
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (escaped, bytes[i]) {
+            (Some(b), _) => {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+            (None, b'+') => out.push(b' '),
+            (None, b) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    pub fn ok(body: impl Into<String>) -> Response {
+        Response::text(200, body)
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// A 200 in the Prometheus text exposition format.
+    pub fn prometheus(body: impl Into<String>) -> Response {
+        Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Response {
+        Response::text(400, message)
+    }
+
+    /// Add an extra response header.
+    pub fn header(mut self, name: &'static str, value: impl ToString) -> Response {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Write the response, omitting the body for `HEAD` requests.
+    pub fn write_to(&self, out: &mut impl Write, head_only: bool, keep_alive: bool) -> io::Result<()> {
+        let extra: String = self
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}\r\n", name, value))
+            .collect();
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: {}\r\n{}\r\n{}",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len(),
+            if keep_alive { "keep-alive" } else { "close" },
+            extra,
+            if head_only { "" } else { &self.body }
+        )?;
+        out.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(raw: &[u8]) -> io::Result<Option<Request>> {
+        read_request(&mut &raw[..])
+    }
+
+    #[test]
+    fn reads_request_line_headers_and_query() {
+        let request = read(b"GET /leak?mb=5&mode=heap HTTP/1.1\r\nHost: x\r\nX-Test:  yes \r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/leak");
+        assert_eq!(request.query("mb"), Some("5"));
+        assert_eq!(request.query("mode"), Some("heap"));
+        assert_eq!(request.query("missing"), None);
+        assert_eq!(request.header("x-test"), Some("yes"));
+        assert!(request.keep_alive());
+    }
+
+    #[test]
+    fn closed_or_empty_connection_is_not_an_error() {
+        assert!(read(b"").unwrap().is_none());
+        assert!(read(b"\r\n\r\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn skips_stray_blank_lines_before_the_request_line() {
+        let request = read(b"\r\n\nGET /status HTTP/1.0\r\n\r\n").unwrap().unwrap();
+        assert_eq!(request.path, "/status");
+        assert!(!request.keep_alive());
+    }
+
+    #[test]
+    fn rejects_malformed_heads() {
+        for raw in [
+            &b"GET /status\r\n\r\n"[..],
+            b"GET /status SPDY/3\r\n\r\n",
+            b"GET / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: ten\r\n\r\n",
+        ] {
+            let err = read(raw).err().expect("should be rejected");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", String::from_utf8_lossy(raw));
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_heads() {
+        let mut raw = b"GET / HTTP/1.1\r\n".to_vec();
+        while raw.len() <= MAX_HEAD {
+            raw.extend_from_slice(b"X-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n");
+        }
+        raw.extend_from_slice(b"\r\n");
+        assert_eq!(read(&raw).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_head_is_unexpected_eof() {
+        let err = read(b"GET / HTTP/1.1\r\nHost: x\r\n").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn reads_pipelined_requests_skipping_bodies() {
+        let raw = b"POST /a HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello\r\n\r\nxGET /b HTTP/1.1\r\nConnection: close\r\n\r\n";
+        let mut reader = &raw[..];
+        let first = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!((first.method.as_str(), first.path.as_str()), ("POST", "/a"));
+        let second = read_request(&mut reader).unwrap().unwrap();
+        assert_eq!((second.method.as_str(), second.path.as_str()), ("GET", "/b"));
+        assert!(!second.keep_alive());
+        assert!(read_request(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn parses_optional_query_values() {
+        let request = Request::parse(b"GET /burn?threads=4&pct=high HTTP/1.1\r\n\r\n");
+        assert_eq!(request.parse_query::<usize>("threads").ok(), Some(Some(4)));
+        assert_eq!(request.parse_query::<usize>("secs").ok(), Some(None));
+        let error = request.parse_query::<u8>("pct").err().unwrap();
+        assert_eq!(error.status(), 400);
+        assert_eq!(error.body(), "invalid pct: invalid digit found in string");
+    }
+
+    #[test]
+    fn percent_decodes_escapes_and_plus() {
+        assert_eq!(percent_decode("a+b%20c%2Fd"), "a b c/d");
+        assert_eq!(percent_decode("%E2%9C%93"), "✓");
+        // Invalid or truncated escapes are kept as written.
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode(""), "");
+    }
+}
@@ -0,0 +1,227 @@
+//! Controlled memory growth for exercising TSPM's `maxMemory` monitor.
+//!
+//! `LEAK_MODE` picks how memory is grown:
+//! - `heap`: filled `Vec<u8>` chunks from the global allocator
+//! - `mmap`: a fresh anonymous mapping per chunk, fully touched
+//! - `touch`: one large `MAP_NORESERVE` reservation whose pages are touched
+//!   progressively, so RSS grows while the virtual size stays flat
+
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::sys;
+
+const MB: usize = 1024 * 1024;
+const PAGE: usize = 4096;
+/// Largest single leak accepted, far beyond any test box but well short of
+/// sizes that overflow the byte count.
+const MAX_MB: f64 = 1024.0 * 1024.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LeakMode {
+    Heap,
+    Mmap,
+    Touch,
+}
+
+impl LeakMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            LeakMode::Heap => "heap",
+            LeakMode::Mmap => "mmap",
+            LeakMode::Touch => "touch",
+        }
+    }
+}
+
+impl FromStr for LeakMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "heap" => Ok(LeakMode::Heap),
+            "mmap" => Ok(LeakMode::Mmap),
+            "touch" => Ok(LeakMode::Touch),
+            other => Err(format!(
+                "unknown leak mode '{}' (expected heap, mmap or touch)",
+                other
+            )),
+        }
+    }
+}
+
+/// A `MAP_NORESERVE` region whose pages are faulted in a slice at a time.
+struct Reservation {
+    base: *mut u8,
+    len: usize,
+    touched: usize,
+}
+
+// SAFETY: the pointer is only dereferenced while holding the `LEAK` lock.
+unsafe impl Send for Reservation {}
+
+struct Leak {
+    heap: Vec<Vec<u8>>,
+    reservation: Option<Reservation>,
+    total: usize,
+}
+
+static LEAK: Mutex<Leak> = Mutex::new(Leak {
+    heap: Vec::new(),
+    reservation: None,
+    total: 0,
+});
+
+/// Total bytes leaked so far across all modes.
+pub fn leaked_bytes() -> usize {
+    LEAK.lock().map(|l| l.total).unwrap_or(0)
+}
+
+/// Leak `mb` megabytes using `mode` and log the new totals.
+pub fn leak_mb(mb: f64, mode: LeakMode) -> Result<usize, String> {
+    if !(0.0..=MAX_MB).contains(&mb) {
+        return Err(format!("cannot leak {} MB (at most {} MB at once)", mb, MAX_MB));
+    }
+    let bytes = (mb * MB as f64) as usize;
+    if bytes == 0 {
+        return Ok(leaked_bytes());
+    }
+
+    let mut leak = LEAK.lock().map_err(|_| "leak state poisoned".to_string())?;
+    match mode {
+        LeakMode::Heap => {
+            let mut chunk = Vec::new();
+            chunk
+                .try_reserve_exact(bytes)
+                .map_err(|e| format!("allocation of {} bytes failed: {}", bytes, e))?;
+            chunk.resize(bytes, 0xA5);
+            leak.heap.push(chunk);
+        }
+        LeakMode::Mmap => {
+            let ptr = sys::map_anonymous(bytes, 0)
+                .ok_or_else(|| format!("mmap of {} bytes failed", bytes))?;
+            // SAFETY: `ptr` is a fresh writable mapping of exactly `bytes` bytes.
+            unsafe { touch(ptr, 0, bytes) };
+        }
+        LeakMode::Touch => {
+            let needs_new = leak
+                .reservation
+                .as_ref()
+                .is_none_or(|r| r.len - r.touched < bytes);
+            if needs_new {
+                let len = bytes.max(config::parse_or("LEAK_RESERVE_MB", 1024usize) * MB);
+                let base = sys::map_anonymous(len, sys::MAP_NORESERVE)
+                    .ok_or_else(|| format!("reservation of {} bytes failed", len))?;
+                leak.reservation = Some(Reservation {
+                    base,
+                    len,
+                    touched: 0,
+                });
+            }
+            if let Some(r) = leak.reservation.as_mut() {
+                // SAFETY: `touched + bytes <= len`, checked above.
+                unsafe { touch(r.base, r.touched, bytes) };
+                r.touched += bytes;
+            }
+        }
+    }
+    leak.total += bytes;
+
+    info!(
+        "💧 Leaked {:.1} MB via {} (total {:.1} MB, rss {})",
+        mb,
+        mode.name(),
+        leak.total as f64 / MB as f64,
+        rss_label()
+    );
+    Ok(leak.total)
+}
+
+/// Start the background leak configured by `LEAK_RATE` (MB per second).
+pub fn start_from_env() {
+    let rate: f64 = config::parse_or("LEAK_RATE", 0.0);
+    if rate <= 0.0 {
+        return;
+    }
+
+    let mode = config::parse_or("LEAK_MODE", LeakMode::Heap);
+    let interval_ms: u64 = config::parse_or("LEAK_INTERVAL_MS", 1000).max(1);
+    let limit_mb: f64 = config::parse_or("LEAK_LIMIT_MB", 0.0);
+    let per_tick = rate * interval_ms as f64 / 1000.0;
+
+    info!(
+        "💧 Leak mode enabled: {} MB/s via {} every {}ms{}",
+        rate,
+        mode.name(),
+        interval_ms,
+        if limit_mb > 0.0 {
+            format!(" up to {} MB", limit_mb)
+        } else {
+            String::new()
+        }
+    );
+
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(interval_ms));
+
+        let total_mb = leaked_bytes() as f64 / MB as f64;
+        if limit_mb > 0.0 && total_mb >= limit_mb {
+            info!("💧 Leak limit of {} MB reached, holding memory", limit_mb);
+            return;
+        }
+        if let Err(e) = leak_mb(per_tick, mode) {
+            error!("Leak failed: {}", e);
+            return;
+        }
+    });
+}
+
+/// `GET /leak?mb=N[&mode=heap|mmap|touch]`
+pub fn handle(request: &Request) -> Result<Response, Response> {
+    let mode = match request.parse_query("mode")? {
+        Some(mode) => mode,
+        None => config::parse_or("LEAK_MODE", LeakMode::Heap),
+    };
+
+    let Some(mb) = request.parse_query::<f64>("mb")? else {
+        return Ok(Response::ok(format!(
+            "Leaked {:.1} MB so far (rss {})",
+            leaked_bytes() as f64 / MB as f64,
+            rss_label()
+        )));
+    };
+    if !(mb > 0.0 && mb <= MAX_MB) {
+        return Err(Response::bad_request(format!("mb must be a positive number up to {}", MAX_MB)));
+    }
+
+    match leak_mb(mb, mode) {
+        Ok(total) => Ok(Response::ok(format!(
+            "Leaked {:.1} MB via {} (total {:.1} MB, rss {})",
+            mb,
+            mode.name(),
+            total as f64 / MB as f64,
+            rss_label()
+        ))),
+        Err(e) => Ok(Response::text(500, e)),
+    }
+}
+
+fn rss_label() -> String {
+    sys::resident_bytes()
+        .map(|b| format!("{:.1} MB", b as f64 / MB as f64))
+        .unwrap_or_else(|| "n/a".to_string())
+}
+
+/// Write one byte into every page of `[base + offset, base + offset + len)`.
+///
+/// # Safety
+/// The range must lie within a writable mapping owned by the caller.
+unsafe fn touch(base: *mut u8, offset: usize, len: usize) {
+    for page in (offset..offset + len).step_by(PAGE) {
+        base.add(page).write_volatile(0xA5);
+    }
+}
@@ -0,0 +1,321 @@
+//! Which port each instance listens on, selected by `PORT_STRATEGY`:
+//! - `offset`: `PORT + instance`, one port per instance
+//! - `shared`: every instance binds `PORT` with `SO_REUSEPORT`, so the kernel
+//!   balances connections across a cluster (also `SHARED_PORT=true`)
+//! - `ephemeral`: the OS picks a free port, written to `PORT_FILE`
+//!
+//! The instance ID is read from the variable named by `INSTANCE_VAR` (default
+//! `NODE_APP_INSTANCE`), falling back to `TSPM_INSTANCE_ID`.
+//!
+//! An already-bound socket passed with the systemd `LISTEN_FDS` protocol is
+//! used instead of binding, so the port can stay open across restarts.
+//!
+//! With `BIND_RETRY`, a port that is still in use (say, by the instance being
+//! replaced) is retried with exponential backoff instead of exiting.
+//!
+//! With `SOCKET_PATH` set, HTTP is served on that Unix socket instead of a TCP
+//! port. A stale socket file left by a crashed run is removed on start, and
+//! the socket is removed again on graceful exit.
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::os::fd::FromRawFd;
+use std::os::raw::c_int;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config;
+use crate::shutdown;
+use crate::sys;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    Offset,
+    Shared,
+    Ephemeral,
+}
+
+impl Strategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Offset => "offset",
+            Strategy::Shared => "shared",
+            Strategy::Ephemeral => "ephemeral",
+        }
+    }
+
+    /// Port to bind for `instance`; `0` lets the OS choose.
+    pub fn port(self, base: u16, instance: u16) -> u16 {
+        match self {
+            Strategy::Offset => base.saturating_add(instance),
+            Strategy::Shared => base,
+            Strategy::Ephemeral => 0,
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "offset" => Ok(Strategy::Offset),
+            "shared" | "reuseport" => Ok(Strategy::Shared),
+            "ephemeral" | "random" => Ok(Strategy::Ephemeral),
+            other => Err(format!(
+                "unknown port strategy '{}' (expected offset, shared or ephemeral)",
+                other
+            )),
+        }
+    }
+}
+
+/// `SHARED_PORT=true` is shorthand for `PORT_STRATEGY=shared`.
+pub fn strategy() -> Strategy {
+    if config::flag("SHARED_PORT") {
+        return Strategy::Shared;
+    }
+    config::parse_or("PORT_STRATEGY", Strategy::Offset)
+}
+
+/// The instance ID TSPM assigned to this process.
+pub fn instance_id() -> u16 {
+    let name = config::var("INSTANCE_VAR").unwrap_or_else(|| "NODE_APP_INSTANCE".to_string());
+    config::var(&name)
+        .or_else(|| config::var("TSPM_INSTANCE_ID"))
+        .and_then(|id| id.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// First descriptor passed by socket activation (after stdin/stdout/stderr).
+const LISTEN_FDS_START: c_int = 3;
+
+/// Take over a listening socket passed via `LISTEN_FDS`. With several fds,
+/// instance N uses the Nth one. `LISTEN_PID` is optional, since a spawner
+/// cannot always know the PID in advance, but must match when set.
+pub fn inherited(instance: u16) -> Option<io::Result<TcpListener>> {
+    let count: c_int = env::var("LISTEN_FDS").ok()?.trim().parse().ok()?;
+    if let Some(pid) = env::var("LISTEN_PID").ok().and_then(|p| p.trim().parse::<u32>().ok()) {
+        if pid != process::id() {
+            debug!("Ignoring LISTEN_FDS meant for PID {}", pid);
+            return None;
+        }
+    }
+    if count < 1 {
+        return None;
+    }
+
+    let fd = LISTEN_FDS_START + if count > 1 { c_int::from(instance).min(count - 1) } else { 0 };
+    info!("Using socket-activated fd {} ({} passed)", fd, count);
+    Some(sys::set_cloexec(fd).map(|_| {
+        // SAFETY: the activation protocol hands us ownership of this fd.
+        unsafe { TcpListener::from_raw_fd(fd) }
+    }))
+}
+
+/// Bind `addr:port` the way `strategy` asks for.
+pub fn bind(strategy: Strategy, addr: &str, port: u16) -> io::Result<TcpListener> {
+    match strategy {
+        Strategy::Shared => {
+            let resolved: Vec<SocketAddr> = (addr, port).to_socket_addrs()?.collect();
+            let mut last = io::Error::new(io::ErrorKind::AddrNotAvailable, "address did not resolve");
+            for candidate in resolved {
+                match sys::bind_reuseport(candidate) {
+                    Ok(listener) => return Ok(listener),
+                    Err(e) => last = e,
+                }
+            }
+            Err(last)
+        }
+        Strategy::Offset | Strategy::Ephemeral => TcpListener::bind((addr, port)),
+    }
+}
+
+/// A bound listening socket.
+pub enum Listener {
+    Tcp(TcpListener),
+    /// Removes its socket file when dropped.
+    Unix(UnixListener, PathBuf),
+}
+
+/// One accepted connection.
+pub enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Listener {
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Listener::Tcp(l) => l.set_nonblocking(nonblocking),
+            Listener::Unix(l, _) => l.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(l) => l.accept().map(|(s, _)| Stream::Tcp(s)),
+            Listener::Unix(l, _) => l.accept().map(|(s, _)| Stream::Unix(s)),
+        }
+    }
+
+    /// The bound TCP port; `None` for a Unix socket.
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            Listener::Tcp(l) => l.local_addr().ok().map(|a| a.port()),
+            Listener::Unix(..) => None,
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix(_, path) = self {
+            match fs::remove_file(&*path) {
+                Ok(()) => info!("Removed socket {}", path.display()),
+                Err(e) => warn!("Failed to remove socket {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+impl Stream {
+    /// Switch an accepted connection to blocking reads that give up after
+    /// `timeout`.
+    pub fn configure(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_nonblocking(false),
+            Stream::Unix(s) => s.set_nonblocking(false),
+        }
+        .and_then(|_| self.set_read_timeout(timeout))
+    }
+
+    pub fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.set_read_timeout(Some(timeout)),
+            Stream::Unix(s) => s.set_read_timeout(Some(timeout)),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.read(buf),
+            Stream::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(s) => s.write(buf),
+            Stream::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(s) => s.flush(),
+            Stream::Unix(s) => s.flush(),
+        }
+    }
+}
+
+/// `SOCKET_PATH` with `{instance}` substituted, if serving on a Unix socket.
+pub fn socket_path(instance: u16) -> Option<String> {
+    config::var("SOCKET_PATH").map(|p| p.replace("{instance}", &instance.to_string()))
+}
+
+/// Bind a Unix socket at `path`, first removing a stale socket file that
+/// nothing listens on. A live socket is reported as `AddrInUse`, and any
+/// other kind of file is left alone.
+pub fn bind_unix(path: &str) -> io::Result<Listener> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path),
+            ));
+        }
+        match UnixStream::connect(path) {
+            Ok(_) => return Err(io::Error::new(io::ErrorKind::AddrInUse, "socket is in use")),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                info!("Removing stale socket {}", path);
+                fs::remove_file(path)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    Ok(Listener::Unix(listener, Path::new(path).to_path_buf()))
+}
+
+/// How many times to retry an occupied port: `BIND_RETRY=N` retries N
+/// times, `true` forever, and unset or `false` not at all.
+fn bind_retries() -> Option<u32> {
+    match config::var("BIND_RETRY") {
+        None => Some(0),
+        Some(v) => match v.trim().parse() {
+            Ok(n) => Some(n),
+            Err(_) if config::is_truthy(&v) => None,
+            Err(_) => Some(0),
+        },
+    }
+}
+
+/// Run `bind` for `target`, retrying while the address is in use as
+/// `BIND_RETRY` allows. The delay starts at `BIND_RETRY_MS` and doubles up to
+/// `BIND_RETRY_MAX_MS`.
+pub fn bind_retrying<T>(target: &str, mut bind: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let retries = bind_retries();
+    let mut delay = Duration::from_millis(config::parse_or("BIND_RETRY_MS", 100).max(1));
+    let max_delay = Duration::from_millis(config::parse_or("BIND_RETRY_MAX_MS", 5000).max(1));
+    let mut attempt = 0;
+    loop {
+        match bind() {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse && retries.is_none_or(|n| attempt < n) => {
+                attempt += 1;
+                warn!(
+                    "{} is in use, retry {} in {}ms",
+                    target,
+                    attempt,
+                    delay.as_millis()
+                );
+                shutdown::pause(delay);
+                delay = (delay * 2).min(max_delay);
+            }
+            Ok(listener) if attempt > 0 => {
+                info!("Bound {} after {} retries", target, attempt);
+                return Ok(listener);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Write the bound port to `PORT_FILE` (with `{instance}` substituted), or
+/// to a per-instance file in the temp directory.
+pub fn write_port_file(instance: u16, port: u16) {
+    let path = config::var("PORT_FILE")
+        .map(|p| p.replace("{instance}", &instance.to_string()))
+        .unwrap_or_else(|| {
+            env::temp_dir()
+                .join(format!("rust-crash-app-{}.port", instance))
+                .display()
+                .to_string()
+        });
+    // Write then rename, so a reader never sees a half-written file.
+    let partial = format!("{}.tmp", path);
+    match fs::write(&partial, format!("{}\n", port)).and_then(|_| fs::rename(&partial, &path)) {
+        Ok(()) => info!("Wrote port {} to {}", port, path),
+        Err(e) => error!("Failed to write port file {}: {}", path, e),
+    }
+}
@@ -0,0 +1,191 @@
+//! Leveled logging that honours `RUST_LOG` and `LOG_FORMAT`.
+//!
+//! `RUST_LOG` accepts `off`, `error`, `warn`, `info`, `debug` or `trace`, either
+//! bare or as an `env_logger`-style directive (`rust_crash_app=debug,info`).
+//! `LOG_FORMAT=json` emits one object per line with `timestamp`, `level`,
+//! `instance`, `pid` and `message`; the default `text` format prints the
+//! message as-is. Errors and warnings go to stderr, everything else to stdout.
+
+use std::fmt;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::json;
+
+const CRATE_TARGET: &str = "rust_crash_app";
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static JSON: AtomicBool = AtomicBool::new(false);
+static INSTANCE: AtomicU32 = AtomicU32::new(0);
+
+/// Record the instance ID and (re)read `RUST_LOG` / `LOG_FORMAT`.
+pub fn init(instance: u16) {
+    INSTANCE.store(u32::from(instance), Ordering::SeqCst);
+    configure();
+}
+
+/// Re-read `RUST_LOG` / `LOG_FORMAT`, e.g. after a config reload.
+pub fn configure() {
+    let level = config::var("RUST_LOG").map_or(Level::Info as u8, |v| parse_filter(&v));
+    MAX_LEVEL.store(level, Ordering::SeqCst);
+    JSON.store(
+        config::var("LOG_FORMAT").is_some_and(|f| f.trim().eq_ignore_ascii_case("json")),
+        Ordering::SeqCst,
+    );
+}
+
+/// Resolve a `RUST_LOG` value to a maximum level; `0` means off. A directive
+/// for this crate wins over a bare default; other crates' directives are ignored.
+fn parse_filter(filter: &str) -> u8 {
+    let mut default = None;
+    let mut ours = None;
+    for directive in filter.split(',').map(str::trim) {
+        match directive.split_once('=') {
+            Some((target, level)) if target.trim() == CRATE_TARGET => ours = level_value(level),
+            Some(_) => {}
+            None => default = level_value(directive).or(default),
+        }
+    }
+    ours.or(default).unwrap_or(Level::Info as u8)
+}
+
+fn level_value(name: &str) -> Option<u8> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "off" => Some(0),
+        "error" => Some(Level::Error as u8),
+        "warn" => Some(Level::Warn as u8),
+        "info" => Some(Level::Info as u8),
+        "debug" => Some(Level::Debug as u8),
+        "trace" => Some(Level::Trace as u8),
+        _ => None,
+    }
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn write(level: Level, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let line = if JSON.load(Ordering::Relaxed) {
+        json::Object::new()
+            .str("timestamp", &timestamp())
+            .str("level", level.name())
+            .num("instance", INSTANCE.load(Ordering::Relaxed))
+            .num("pid", process::id())
+            .str("message", &args.to_string())
+            .render()
+    } else {
+        args.to_string()
+    };
+
+    if level <= Level::Warn {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Current UTC time as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil-from-days (Howard Hinnant), valid for any date after 1970.
+    let z = days as i64 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        now.subsec_millis()
+    )
+}
+
+// Textually scoped via `#[macro_use]` in `main.rs`: a path import of `warn`
+// would clash with the built-in `#[warn]` attribute.
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Error, format_args!($($arg)*)) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Warn, format_args!($($arg)*)) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Info, format_args!($($arg)*)) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Debug, format_args!($($arg)*)) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_levels() {
+        assert_eq!(parse_filter("off"), 0);
+        assert_eq!(parse_filter("error"), Level::Error as u8);
+        assert_eq!(parse_filter(" DEBUG "), Level::Debug as u8);
+        assert_eq!(parse_filter("trace"), Level::Trace as u8);
+    }
+
+    #[test]
+    fn crate_directive_wins_over_default() {
+        assert_eq!(parse_filter("rust_crash_app=debug,warn"), Level::Debug as u8);
+        assert_eq!(parse_filter("warn, rust_crash_app = trace"), Level::Trace as u8);
+        assert_eq!(parse_filter("rust_crash_app=off,info"), 0);
+    }
+
+    #[test]
+    fn other_crates_and_garbage_fall_back() {
+        assert_eq!(parse_filter("hyper=trace,error"), Level::Error as u8);
+        assert_eq!(parse_filter("hyper=trace"), Level::Info as u8);
+        assert_eq!(parse_filter("loud"), Level::Info as u8);
+        assert_eq!(parse_filter(""), Level::Info as u8);
+    }
+}
@@ -0,0 +1,296 @@
+//! Configurable stdout/stderr load for reproducing `ProcessLogStreamer`
+//! rotation, back-pressure and decoding bugs on demand.
+//!
+//! Shapes:
+//! - `lines`: plain newline-terminated lines of `bytes` length
+//! - `long`: `bytes` of payload with no newline at all
+//! - `interleaved`: stdout and stderr lines written in alternating halves
+//! - `binary`: random bytes (invalid UTF-8, NUL, `\r`) followed by a newline
+//! - `utf8-split`: multi-byte UTF-8 flushed with a write boundary mid-character
+//! - `mixed`: cycles through all of the above
+
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::rng::Rng;
+
+const TICK: Duration = Duration::from_millis(10);
+/// Pause between the halves of a split write so the reader sees two chunks.
+/// Highest accepted rate, in lines per second.
+const MAX_RATE: f64 = 1_000_000.0;
+const SPLIT_PAUSE: Duration = Duration::from_millis(2);
+const UTF8_SAMPLE: &str = "héllo wörld — 日本語テキスト 🚀🦀 ünïcödé ";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Lines,
+    Long,
+    Interleaved,
+    Binary,
+    Utf8Split,
+    Mixed,
+}
+
+impl Shape {
+    const CYCLE: [Shape; 5] = [
+        Shape::Lines,
+        Shape::Long,
+        Shape::Interleaved,
+        Shape::Binary,
+        Shape::Utf8Split,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Shape::Lines => "lines",
+            Shape::Long => "long",
+            Shape::Interleaved => "interleaved",
+            Shape::Binary => "binary",
+            Shape::Utf8Split => "utf8-split",
+            Shape::Mixed => "mixed",
+        }
+    }
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lines" => Ok(Shape::Lines),
+            "long" => Ok(Shape::Long),
+            "interleaved" => Ok(Shape::Interleaved),
+            "binary" => Ok(Shape::Binary),
+            "utf8-split" | "utf8" => Ok(Shape::Utf8Split),
+            "mixed" => Ok(Shape::Mixed),
+            other => Err(format!(
+                "unknown log shape '{}' (expected lines, long, interleaved, binary, utf8-split or mixed)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Flood {
+    rate: f64,
+    shape: Shape,
+    bytes: usize,
+    secs: u64,
+    seed: Option<u64>,
+}
+
+impl Flood {
+    fn describe(&self) -> String {
+        let duration = if self.secs == 0 {
+            "until stopped".to_string()
+        } else {
+            format!("for {}s", self.secs)
+        };
+        format!(
+            "{} line(s)/s of {} shape, {} bytes each, {}",
+            self.rate,
+            self.shape.name(),
+            self.bytes,
+            duration
+        )
+    }
+}
+
+/// Bumped on every start/stop; flood threads exit once it moves past theirs.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static CURRENT: Mutex<Option<Flood>> = Mutex::new(None);
+
+fn start(flood: Flood) {
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let running = flood.rate > 0.0;
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = running.then_some(flood);
+    }
+
+    if !running {
+        info!("📜 Log flood stopped");
+        return;
+    }
+
+    info!("📜 Log flood: {}", flood.describe());
+    thread::spawn(move || run(flood, generation));
+}
+
+fn run(flood: Flood, generation: u64) {
+    let mut rng = flood.seed.map(Rng::new).unwrap_or_else(Rng::from_entropy);
+    let deadline = (flood.secs > 0).then(|| Instant::now() + Duration::from_secs(flood.secs));
+    let mut last = Instant::now();
+    let mut budget = 0.0;
+    let mut seq: u64 = 0;
+
+    while GENERATION.load(Ordering::SeqCst) == generation {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            if let Ok(mut current) = CURRENT.lock() {
+                *current = None;
+            }
+            info!("📜 Log flood finished after {} line(s)", seq);
+            return;
+        }
+
+        thread::sleep(TICK);
+        let now = Instant::now();
+        // Carry at most a second's worth, so a blocked stdout does not build
+        // up a backlog that is written in one burst later.
+        budget = (budget + flood.rate * now.duration_since(last).as_secs_f64()).min(flood.rate.max(1.0));
+        last = now;
+
+        while budget >= 1.0 {
+            if GENERATION.load(Ordering::SeqCst) != generation || deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+            budget -= 1.0;
+            seq += 1;
+            let shape = match flood.shape {
+                Shape::Mixed => Shape::CYCLE[(seq as usize) % Shape::CYCLE.len()],
+                shape => shape,
+            };
+            // A closed pipe means nobody is reading any more; stop quietly.
+            if emit(shape, seq, flood.bytes, &mut rng).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn emit(shape: Shape, seq: u64, bytes: usize, rng: &mut Rng) -> io::Result<()> {
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+
+    match shape {
+        Shape::Lines | Shape::Mixed => {
+            let mut out = stdout.lock();
+            out.write_all(&padded(format!("[flood {}] ", seq), bytes))?;
+            out.write_all(b"\n")?;
+            out.flush()
+        }
+        Shape::Long => {
+            let mut out = stdout.lock();
+            out.write_all(&padded(format!("[flood {} long] ", seq), bytes))?;
+            out.flush()
+        }
+        Shape::Interleaved => {
+            let line_out = padded(format!("[flood {} stdout] ", seq), bytes);
+            let line_err = padded(format!("[flood {} stderr] ", seq), bytes);
+            let (out_a, out_b) = line_out.split_at(line_out.len() / 2);
+            let (err_a, err_b) = line_err.split_at(line_err.len() / 2);
+            write_flush(&mut stdout.lock(), out_a)?;
+            write_flush(&mut stderr.lock(), err_a)?;
+            write_flush(&mut stdout.lock(), out_b)?;
+            write_flush(&mut stdout.lock(), b"\n")?;
+            write_flush(&mut stderr.lock(), err_b)?;
+            write_flush(&mut stderr.lock(), b"\n")
+        }
+        Shape::Binary => {
+            let mut garbage = vec![0u8; bytes];
+            rng.fill(&mut garbage);
+            garbage.push(b'\n');
+            write_flush(&mut stdout.lock(), &garbage)
+        }
+        Shape::Utf8Split => {
+            let mut line = format!("[flood {} utf8] ", seq);
+            while line.len() < bytes {
+                line.push_str(UTF8_SAMPLE);
+            }
+            line.push('\n');
+            let raw = line.as_bytes();
+            // Cut inside the first multi-byte character past the prefix.
+            let cut = raw
+                .iter()
+                .position(|b| *b >= 0x80)
+                .map(|p| p + 1)
+                .unwrap_or(raw.len() / 2);
+            let mut out = stdout.lock();
+            write_flush(&mut out, &raw[..cut])?;
+            thread::sleep(SPLIT_PAUSE);
+            write_flush(&mut out, &raw[cut..])
+        }
+    }
+}
+
+fn write_flush(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(bytes)?;
+    out.flush()
+}
+
+/// `prefix` padded with a repeating alphabet up to `len` bytes.
+fn padded(prefix: String, len: usize) -> Vec<u8> {
+    let mut line = prefix.into_bytes();
+    let alphabet = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut i = 0;
+    while line.len() < len {
+        line.push(alphabet[i % alphabet.len()]);
+        i += 1;
+    }
+    line
+}
+
+fn default_bytes(shape: Shape) -> usize {
+    match shape {
+        Shape::Long => 256 * 1024,
+        _ => 120,
+    }
+}
+
+/// Start the flood configured by `LOG_FLOOD_RATE` and friends.
+pub fn start_from_env() {
+    let rate: f64 = config::parse_or("LOG_FLOOD_RATE", 0.0);
+    if rate.is_nan() || rate <= 0.0 {
+        return;
+    }
+    if rate > MAX_RATE {
+        warn!("Ignoring LOG_FLOOD_RATE {} (at most {} lines/s)", rate, MAX_RATE);
+        return;
+    }
+    let shape = config::parse_or("LOG_FLOOD_SHAPE", Shape::Lines);
+    start(Flood {
+        rate,
+        shape,
+        bytes: config::parse_or("LOG_FLOOD_BYTES", default_bytes(shape)),
+        secs: config::parse_or("LOG_FLOOD_SECS", 0),
+        seed: config::var("LOG_FLOOD_SEED").and_then(|s| s.trim().parse().ok()),
+    });
+}
+
+/// `GET /logs?rate=N&shape=S&bytes=B&secs=T&seed=X`
+pub fn handle(request: &Request) -> Result<Response, Response> {
+    let Some(rate) = request.parse_query::<f64>("rate")? else {
+        let status = match CURRENT.lock().ok().and_then(|c| *c) {
+            Some(flood) => format!("Flooding {}", flood.describe()),
+            None => "Not flooding".to_string(),
+        };
+        return Ok(Response::ok(status));
+    };
+    if !(0.0..=MAX_RATE).contains(&rate) {
+        return Err(Response::bad_request(format!("rate must be a number from 0 to {}", MAX_RATE)));
+    }
+    let shape = request.parse_query("shape")?.unwrap_or(Shape::Lines);
+    let bytes = request.parse_query("bytes")?.unwrap_or_else(|| default_bytes(shape));
+    let secs = request.parse_query("secs")?.unwrap_or(0);
+    let seed = request.parse_query("seed")?;
+
+    let flood = Flood {
+        rate,
+        shape,
+        bytes,
+        secs,
+        seed,
+    };
+    start(flood);
+    if rate == 0.0 {
+        Ok(Response::ok("Log flood stopped"))
+    } else {
+        Ok(Response::ok(format!("Flooding {}", flood.describe())))
+    }
+}
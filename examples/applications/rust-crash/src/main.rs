@@ -1,62 +1,334 @@
+#[macro_use]
+mod log;
+
+mod burn;
+mod children;
+mod cli;
+mod config;
+mod console;
+mod context;
+mod crashes;
+mod exits;
+mod hang;
+mod health;
+mod http;
+mod json;
+mod leak;
+mod listener;
+mod logflood;
+mod metrics;
+mod notify;
+mod pool;
+mod rng;
+mod scenario;
+mod shutdown;
+mod signals;
+mod sys;
+mod watchdog;
+
 use std::env;
-use std::io::prelude::*;
-use std::net::TcpListener;
+use std::io::{BufReader, ErrorKind};
+use std::panic;
 use std::process;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use http::{Request, Response};
+
+/// Identity of this instance, shared with every request handler.
+#[derive(Clone, Copy)]
+struct App {
+    instance: u16,
+    /// `None` when serving on a Unix socket.
+    port: Option<u16>,
+    socket: Option<&'static str>,
+    started: Instant,
+}
+
+/// How often the non-blocking accept loop checks for pending signals.
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
 
 fn main() {
-    let _args: Vec<String> = env::args().collect();
-    
-    // Support NODE_APP_INSTANCE environment variable from TSPM
-    let base_port_str = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let base_port: u16 = base_port_str.parse().unwrap_or(8080);
-    
-    let instance_offset_str = env::var("NODE_APP_INSTANCE").unwrap_or_else(|_| "0".to_string());
-    let instance_offset: u16 = instance_offset_str.parse().unwrap_or(0);
-    
-    let port = base_port + instance_offset;
-    
-    println!("Rust app starting on port {} (base={}, instance={})", port, base_port, instance_offset);
-
-    thread::sleep(Duration::from_millis(200));
-
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port));
-    
-    match listener {
+    // Requests and crash schedules run on their own threads; a panic in any
+    // of them must still take the whole process down so TSPM sees the crash.
+    // Installed first so even an immediate `CRASH_AFTER_MS=0` is covered. With
+    // `panic = "abort"` the runtime aborts on its own after the hook.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        #[cfg(panic = "unwind")]
+        process::exit(101);
+    }));
+
+    // Worker re-executions of this binary branch off before any server setup.
+    children::run_role();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    cli::apply(&args);
+    let loaded = config::load();
+    let instance_offset = listener::instance_id();
+    let scenario = scenario::load(instance_offset);
+
+    let base_port: u16 = config::parse_or("PORT", 8080);
+    let strategy = listener::strategy();
+    let port = strategy.port(base_port, instance_offset);
+    // Leaked once so `App` stays `Copy` for the route closures.
+    let socket_path: Option<&'static str> =
+        listener::socket_path(instance_offset).map(|path| &*Box::leak(path.into_boxed_str()));
+
+    log::init(instance_offset);
+
+    match (socket_path, strategy) {
+        (Some(path), _) => info!("Rust app starting on socket {} (instance={})", path, instance_offset),
+        (None, listener::Strategy::Offset) => info!(
+            "Rust app starting on port {} (base={}, instance={})",
+            port, base_port, instance_offset
+        ),
+        (None, _) => info!(
+            "Rust app starting on port {} ({} port, instance={})",
+            port,
+            strategy.name(),
+            instance_offset
+        ),
+    }
+
+    exits::exit_on_start();
+    crashes::start(instance_offset);
+
+    let mut app = App {
+        instance: instance_offset,
+        port: socket_path.is_none().then_some(port),
+        socket: socket_path,
+        started: Instant::now(),
+    };
+
+    match loaded {
+        Ok(0) => {}
+        Ok(n) => info!("Loaded {} value(s) from {}", n, config::env_file().unwrap_or_default()),
+        Err(e) => error!("Failed to load ENV_FILE: {}", e),
+    }
+    match scenario {
+        Ok(None) => {}
+        Ok(Some((name, steps))) => info!("🎬 Scenario {} with {} step(s)", name, steps),
+        Err(e) => {
+            error!("Failed to load SCENARIO: {}", e);
+            process::exit(2);
+        }
+    }
+
+    signals::install(&[sys::SIGTERM, sys::SIGINT, sys::SIGHUP, sys::SIGUSR1]);
+
+    leak::start_from_env();
+    burn::start_from_env();
+    logflood::start_from_env();
+    health::start_script_from_env();
+    hang::start_from_env();
+    children::start_from_env();
+
+    shutdown::pause(Duration::from_millis(config::parse_or("STARTUP_DELAY_MS", 200)));
+
+    let bind_addr = config::var("BIND_ADDR").unwrap_or_else(|| "0.0.0.0".to_string());
+    let target = match socket_path {
+        Some(path) => format!("socket {}", path),
+        None => format!("{}:{}", bind_addr, port),
+    };
+    let bound = match socket_path {
+        Some(path) => listener::bind_retrying(&target, || listener::bind_unix(path)),
+        None => listener::inherited(instance_offset)
+            .unwrap_or_else(|| listener::bind_retrying(&target, || listener::bind(strategy, &bind_addr, port)))
+            .map(listener::Listener::Tcp),
+    }
+    .and_then(|l| l.set_nonblocking(true).map(|_| l));
+
+    match bound {
         Ok(l) => {
-            println!("Server process PID: {}", process::id());
-            
-            for stream in l.incoming() {
-                match stream {
-                    Ok(mut stream) => {
-                        let mut buffer = [0; 1024];
-                        stream.read(&mut buffer).unwrap();
-                        let request = String::from_utf8_lossy(&buffer);
-                        
-                        // Default response
-                        let response = format!("HTTP/1.1 200 OK\r\n\r\nHello from Rust instance {}!", instance_offset);
-                        stream.write(response.as_bytes()).unwrap();
-                        stream.flush().unwrap();
-                        
-                        // Only crash if enabled AND specifically requested via /crash path
-                        if env::var("ENABLE_CRASH").unwrap_or_default() == "true" {
-                            if request.contains("GET /crash") {
-                                println!("⚠️  Received CRASH command for instance {}!", instance_offset);
-                                thread::sleep(Duration::from_millis(100));
-                                panic!("Intentional crash triggered via /crash endpoint!");
-                            }
+            info!("Server process PID: {}", process::id());
+            match l.port() {
+                Some(bound_port) => {
+                    app.port = Some(bound_port);
+                    if strategy == listener::Strategy::Ephemeral {
+                        listener::write_port_file(instance_offset, bound_port);
+                    }
+                    notify::ready(format!("port {}", bound_port));
+                }
+                None => {
+                    info!("Listening on {}", target);
+                    notify::ready(target.clone());
+                }
+            }
+            watchdog::start_from_env();
+            console::start(app.instance, move |request| route(request, app));
+            scenario::start(app.instance, app.started, move |request| route(request, app));
+            let pool = pool::Pool::new(config::parse_or("HTTP_THREADS", 64));
+            let keep_alive = Duration::from_millis(config::parse_or("KEEP_ALIVE_MS", 5000).max(1));
+
+            loop {
+                if let Some(sig) = signals::take(&[sys::SIGTERM, sys::SIGINT]) {
+                    if shutdown::should_stop(sig) {
+                        // Closing the listener refuses new connections while
+                        // in-flight requests drain.
+                        drop(l);
+                        shutdown::finish(sig);
+                    }
+                }
+                if signals::take(&[sys::SIGHUP]).is_some() {
+                    reload();
+                }
+                if signals::take(&[sys::SIGUSR1]).is_some() {
+                    hang::set(hang::Mode::None, None);
+                    if watchdog::stopped() {
+                        watchdog::set_stopped(false, None);
+                    }
+                }
+
+                // Leave connections queued in the kernel backlog.
+                if hang::mode() == hang::Mode::NoAccept {
+                    thread::sleep(ACCEPT_POLL);
+                    continue;
+                }
+
+                match l.accept() {
+                    Ok(stream) => {
+                        // Idle keep-alive connections give their worker back
+                        // once the read timeout expires.
+                        match stream.configure(keep_alive) {
+                            Ok(()) => pool.execute(move || handle_connection(stream, app)),
+                            Err(e) => debug!("Dropping connection: {}", e),
                         }
                     }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
                     Err(e) => {
-                        eprintln!("Connection failed: {}", e);
+                        error!("Connection failed: {}", e);
                     }
                 }
             }
         },
         Err(e) => {
-            eprintln!("Failed to bind port {}: {}", port, e);
+            error!("Failed to bind {}: {}", target, e);
             process::exit(1);
         }
     }
 }
+
+/// Serve requests on one connection until the client closes it, it goes
+/// idle, or either side opts out of keep-alive.
+fn handle_connection(stream: listener::Stream, app: App) {
+    let connection = metrics::connection();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let request = match http::read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                let _ = Response::bad_request(e.to_string()).write_to(reader.get_mut(), false, false);
+                return;
+            }
+            Err(e) => {
+                debug!("Dropping connection: {}", e);
+                return;
+            }
+        };
+
+        let in_flight = shutdown::track();
+        if let Some(mode) = hang::intercept(&request) {
+            hang::hold(mode, reader.into_inner(), (connection, in_flight));
+            return;
+        }
+
+        let started = Instant::now();
+        // Tells clients which instance answered when several share a port.
+        let response = route(&request, app)
+            .header("X-Instance-Id", app.instance)
+            .header("X-Pid", process::id());
+        let keep_alive = request.keep_alive() && !shutdown::stopping();
+        let written = response.write_to(reader.get_mut(), request.method == "HEAD", keep_alive);
+        metrics::observe(started.elapsed());
+        crashes::count_request(&request, app.instance);
+        debug!(
+            "{} {} -> {} in {}ms",
+            request.method,
+            request.path,
+            response.status(),
+            started.elapsed().as_millis()
+        );
+
+        if let Err(e) = written {
+            debug!("Failed to write response: {}", e);
+            return;
+        }
+        if !keep_alive {
+            return;
+        }
+    }
+}
+
+fn route(request: &Request, app: App) -> Response {
+    if let Some(response) = exits::handle(request, app.instance) {
+        return response;
+    }
+
+    // Handlers answer a bad query parameter with `Err` carrying the 400.
+    let handled = match request.path.as_str() {
+        // Only crash if enabled AND specifically requested via /crash path
+        "/crash" if config::flag("ENABLE_CRASH") && request.method == "GET" => {
+            exits::schedule(exits::Exit::Panic, app.instance);
+            Ok(Response::ok(format!("Hello from Rust instance {}!", app.instance)))
+        }
+        "/leak" => leak::handle(request),
+        "/burn" => burn::handle(request),
+        "/logs" => logflood::handle(request),
+        "/health" => Ok(health::serve(health::Kind::Health, request)),
+        "/ready" => Ok(health::serve(health::Kind::Ready, request)),
+        "/admin/health" => health::admin(request),
+        "/admin/health/script" => health::admin_script(request),
+        "/admin/hang" => hang::admin(request),
+        "/admin/watchdog" => watchdog::admin(request),
+        "/children" => children::handle(request),
+        "/slow" => slow(request),
+        "/status" => Ok(status(app)),
+        "/env" => Ok(context::env()),
+        "/context" => Ok(context::context(app.instance, app.port, app.socket)),
+        "/metrics" => Ok(Response::prometheus(metrics::render(app.instance, app.started.elapsed()))),
+        // Default response
+        _ => Ok(Response::ok(format!("Hello from Rust instance {}!", app.instance))),
+    };
+    handled.unwrap_or_else(|bad_request| bad_request)
+}
+
+/// `GET /slow?ms=N` keeps a request in flight, e.g. to observe a drain.
+fn slow(request: &Request) -> Result<Response, Response> {
+    let ms = request.parse_query::<u64>("ms")?.unwrap_or(1000);
+    thread::sleep(Duration::from_millis(ms));
+    Ok(Response::ok(format!("Slept {}ms", ms)))
+}
+
+/// Re-read `ENV_FILE` in place; the listening socket is left untouched.
+fn reload() {
+    match config::reload() {
+        Ok((generation, count)) => {
+            log::configure();
+            info!(
+                "🔄 RELOAD generation={} ({} value(s) from {})",
+                generation,
+                count,
+                config::env_file().unwrap_or_default()
+            );
+        }
+        Err(e) => error!("🔄 RELOAD failed, keeping generation {}: {}", config::generation(), e),
+    }
+}
+
+/// `GET /status`
+fn status(app: App) -> Response {
+    let body = json::Object::new()
+        .num("pid", process::id())
+        .num("instance", app.instance)
+        .opt_num("port", app.port)
+        .opt_str("socket", app.socket)
+        .num("uptimeMs", app.started.elapsed().as_millis())
+        .num("reloadGeneration", config::generation())
+        .opt_str("envFile", config::env_file().as_deref())
+        .num("inFlight", shutdown::in_flight())
+        .num("leakedBytes", leak::leaked_bytes());
+    Response::json(200, body.render())
+}
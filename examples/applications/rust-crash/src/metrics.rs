@@ -0,0 +1,149 @@
+//! Self-reported metrics in Prometheus text format, so TSPM's own monitoring
+//! and its `least-connections` strategy can be checked against what the
+//! instance says about itself.
+//!
+//! Every series carries `process` and `instance` labels taken from
+//! `TSPM_PROCESS_NAME` and `TSPM_INSTANCE_ID`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::config;
+use crate::leak;
+use crate::shutdown;
+use crate::sys;
+
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+const BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10_000];
+
+static ACTIVE_CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+static CONNECTIONS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static REQUESTS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static LATENCY_SUM_MICROS: AtomicU64 = AtomicU64::new(0);
+static LATENCY_BUCKETS: [AtomicU64; BUCKETS_MS.len()] = [const { AtomicU64::new(0) }; BUCKETS_MS.len()];
+
+/// Marks one connection as open until dropped.
+pub struct Connection(());
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn connection() -> Connection {
+    ACTIVE_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
+    CONNECTIONS_TOTAL.fetch_add(1, Ordering::SeqCst);
+    Connection(())
+}
+
+/// Record one answered request and how long it took.
+pub fn observe(latency: Duration) {
+    REQUESTS_TOTAL.fetch_add(1, Ordering::SeqCst);
+    LATENCY_SUM_MICROS.fetch_add(latency.as_micros() as u64, Ordering::SeqCst);
+    // Buckets are stored non-cumulatively and summed when rendered.
+    if let Some(i) = bucket(latency) {
+        LATENCY_BUCKETS[i].fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// The first bucket whose bound holds `latency`, compared in microseconds so
+/// a 5.9ms request does not truncate into `le="0.005"`.
+fn bucket(latency: Duration) -> Option<usize> {
+    let micros = latency.as_micros();
+    BUCKETS_MS.iter().position(|&bound| micros <= u128::from(bound) * 1000)
+}
+
+/// Render all metrics in the Prometheus text exposition format.
+pub fn render(instance: u16, uptime: Duration) -> String {
+    let process = config::var("TSPM_PROCESS_NAME").unwrap_or_else(|| "rust-crash-app".to_string());
+    let instance = config::var("TSPM_INSTANCE_ID").unwrap_or_else(|| instance.to_string());
+    let labels = format!("process=\"{}\",instance=\"{}\"", escape(&process), escape(&instance));
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{}{{{}}} {}", name, help, name, kind, name, labels, value);
+    };
+    metric(
+        "rust_crash_connections_active",
+        "gauge",
+        "Open client connections.",
+        ACTIVE_CONNECTIONS.load(Ordering::SeqCst),
+    );
+    metric(
+        "rust_crash_connections_total",
+        "counter",
+        "Client connections accepted.",
+        CONNECTIONS_TOTAL.load(Ordering::SeqCst),
+    );
+    metric(
+        "rust_crash_requests_total",
+        "counter",
+        "HTTP requests answered.",
+        REQUESTS_TOTAL.load(Ordering::SeqCst),
+    );
+    metric(
+        "rust_crash_requests_in_flight",
+        "gauge",
+        "HTTP requests currently being handled.",
+        shutdown::in_flight() as u64,
+    );
+    metric(
+        "rust_crash_leaked_bytes",
+        "gauge",
+        "Bytes deliberately leaked so far.",
+        leak::leaked_bytes() as u64,
+    );
+    metric(
+        "process_resident_memory_bytes",
+        "gauge",
+        "Resident set size in bytes.",
+        sys::resident_bytes().unwrap_or(0),
+    );
+    metric(
+        "process_threads",
+        "gauge",
+        "OS threads in the process.",
+        sys::thread_count().unwrap_or(0),
+    );
+    metric(
+        "process_uptime_seconds",
+        "gauge",
+        "Seconds since the process started.",
+        uptime.as_secs(),
+    );
+
+    let name = "rust_crash_request_duration_seconds";
+    let _ = writeln!(out, "# HELP {} HTTP request latency.\n# TYPE {} histogram", name, name);
+    let mut cumulative = 0;
+    for (bound, count) in BUCKETS_MS.iter().zip(&LATENCY_BUCKETS) {
+        cumulative += count.load(Ordering::SeqCst);
+        let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, *bound as f64 / 1000.0, cumulative);
+    }
+    let total = REQUESTS_TOTAL.load(Ordering::SeqCst);
+    let sum = LATENCY_SUM_MICROS.load(Ordering::SeqCst) as f64 / 1_000_000.0;
+    let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, total);
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, sum);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, total);
+    out
+}
+
+/// Escape a Prometheus label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_compares_without_truncating() {
+        assert_eq!(bucket(Duration::from_micros(5_000)), Some(0));
+        assert_eq!(bucket(Duration::from_micros(5_001)), Some(1));
+        assert_eq!(bucket(Duration::from_micros(5_900)), Some(1));
+        assert_eq!(bucket(Duration::from_millis(10_000)), Some(BUCKETS_MS.len() - 1));
+        assert_eq!(bucket(Duration::from_millis(10_001)), None);
+    }
+}
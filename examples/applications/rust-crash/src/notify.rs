@@ -0,0 +1,107 @@
+//! Readiness notification for supervisors without an IPC channel to a native
+//! binary. Once the listener is bound (plus `READY_DELAY_MS`), the fixture
+//! prints the `TSPM_READY` line TSPM watches for on stdout and, when
+//! `NOTIFY_SOCKET` is set, sends sd_notify's `READY=1` datagram.
+//!
+//! `READY_NOTIFY` picks the channels: `auto` (default: both, the socket only
+//! when set), `stdout`, `socket` or `none`.
+
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+
+/// Must match `READY_PROTOCOL.STDOUT_SENTINEL` in TSPM.
+pub const STDOUT_SENTINEL: &str = "TSPM_READY";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channels {
+    Auto,
+    Stdout,
+    Socket,
+    None,
+}
+
+impl FromStr for Channels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" | "both" => Ok(Channels::Auto),
+            "stdout" => Ok(Channels::Stdout),
+            "socket" => Ok(Channels::Socket),
+            "none" | "off" => Ok(Channels::None),
+            other => Err(format!(
+                "unknown readiness channel '{}' (expected auto, stdout, socket or none)",
+                other
+            )),
+        }
+    }
+}
+
+/// Send `state` (newline-separated `KEY=VALUE` pairs) to `NOTIFY_SOCKET`.
+/// Returns `Ok(false)` when no socket is configured.
+pub fn send(state: &str) -> io::Result<bool> {
+    let Some(path) = config::var("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    let socket = UnixDatagram::unbound()?;
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), &path)?;
+        }
+    }
+    Ok(true)
+}
+
+/// The channels selected by `READY_NOTIFY`.
+pub fn channels() -> Channels {
+    config::parse_or("READY_NOTIFY", Channels::Auto)
+}
+
+/// Send `state` to the socket and `line` to stdout, as `channels` allows.
+/// Returns whether anything was sent.
+pub fn announce(channels: Channels, state: &str, line: &str) -> bool {
+    let mut sent = false;
+    if matches!(channels, Channels::Auto | Channels::Socket) {
+        match send(state) {
+            Ok(true) => sent = true,
+            Ok(false) if channels == Channels::Socket => warn!("READY_NOTIFY=socket but NOTIFY_SOCKET is not set"),
+            Ok(false) => {}
+            Err(e) => error!("Failed to notify NOTIFY_SOCKET: {}", e),
+        }
+    }
+    if matches!(channels, Channels::Auto | Channels::Stdout) {
+        // A bare line, never wrapped in JSON, so the supervisor can match it.
+        let mut stdout = io::stdout().lock();
+        sent |= writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_ok();
+    }
+    sent
+}
+
+/// Announce readiness on a background thread once `READY_DELAY_MS` elapses.
+pub fn ready(address: String) {
+    let channels = channels();
+    let delay: u64 = config::parse_or("READY_DELAY_MS", 0);
+    if channels == Channels::None {
+        return;
+    }
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(delay));
+        let state = format!("READY=1\nMAINPID={}\nSTATUS=Listening on {}", process::id(), address);
+        if announce(channels, &state, STDOUT_SENTINEL) {
+            info!("Announced readiness on {}", address);
+        }
+    });
+}
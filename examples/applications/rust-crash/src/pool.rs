@@ -0,0 +1,47 @@
+//! Fixed-size worker pool for serving connections, so a burst of clients is
+//! queued instead of spawning a thread each.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub struct Pool {
+    jobs: Sender<Job>,
+}
+
+impl Pool {
+    pub fn new(size: usize) -> Pool {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..size.max(1) {
+            let queue = Arc::clone(&queue);
+            thread::Builder::new()
+                .name(format!("http-{}", i))
+                .spawn(move || work(&queue))
+                .expect("failed to spawn HTTP worker");
+        }
+        Pool { jobs }
+    }
+
+    /// Queue `job` for the next idle worker.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        // Workers never hang up while the pool exists.
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
+
+fn work(queue: &Mutex<Receiver<Job>>) {
+    loop {
+        // Hold the lock only while waiting, not while running the job.
+        let job = match queue.lock() {
+            Ok(queue) => queue.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
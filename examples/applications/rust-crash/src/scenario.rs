@@ -0,0 +1,151 @@
+//! Scenario files: a reproducible failure story kept in one JSON file instead
+//! of knobs spread across env vars. `SCENARIO=path` loads
+//!
+//! ```json
+//! {
+//!   "name": "leak-then-exit",
+//!   "env": { "STARTUP_DELAY_MS": 3000 },
+//!   "steps": [
+//!     { "at": "20s", "every": "1s", "until": "40s", "do": "leak 5" },
+//!     { "at": "40s", "do": "health down" },
+//!     { "at": "60s", "do": "exit 137" }
+//!   ],
+//!   "instances": { "1": { "steps": [{ "at": "10s", "do": "crash" }] } }
+//! }
+//! ```
+//!
+//! `env` sets knobs below `ENV_FILE` and flags. Each step runs a console
+//! command at `at` after startup, repeating every `every` until `until` if
+//! given. An entry under `instances` adds its `env` and `steps` for that
+//! instance ID only.
+
+use std::fs;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::console;
+use crate::http::{Request, Response};
+use crate::json::{self, Value};
+
+struct Step {
+    at: Duration,
+    every: Option<Duration>,
+    until: Option<Duration>,
+    command: String,
+}
+
+static STEPS: OnceLock<Vec<Step>> = OnceLock::new();
+
+/// A duration string (`500ms`, `10s`, `2m`) or a number of milliseconds.
+fn duration(step: &Value, key: &str) -> Result<Option<Duration>, String> {
+    let Some(value) = step.get(key) else {
+        return Ok(None);
+    };
+    let parsed = match value {
+        Value::Number(ms) if *ms >= 0.0 => Some(Duration::from_micros((ms * 1000.0) as u64)),
+        Value::String(s) => config::parse_duration(s),
+        _ => None,
+    };
+    parsed
+        .map(Some)
+        .ok_or_else(|| format!("'{}' must be a duration like \"10s\" or milliseconds", key))
+}
+
+fn parse_step(step: &Value) -> Result<Step, String> {
+    let command = step
+        .get("do")
+        .and_then(Value::as_str)
+        .ok_or("every step needs a \"do\" command")?;
+    console::check(command).map_err(|e| format!("\"{}\": {}", command, e))?;
+    let every = duration(step, "every")?;
+    if every == Some(Duration::ZERO) {
+        return Err(format!("\"{}\": 'every' must be positive", command));
+    }
+    Ok(Step {
+        at: duration(step, "at")?.unwrap_or_default(),
+        every,
+        until: duration(step, "until")?,
+        command: command.to_string(),
+    })
+}
+
+/// Add a section's `env` and `steps` (the top level or one instance entry).
+fn collect(section: &Value, env: &mut Vec<(String, String)>, steps: &mut Vec<Step>) -> Result<(), String> {
+    if let Some(vars) = section.get("env") {
+        let vars = vars.as_object().ok_or("'env' must be an object")?;
+        for (name, value) in vars {
+            let value = value
+                .to_scalar_string()
+                .ok_or_else(|| format!("env {} must be a string, number or boolean", name))?;
+            env.push((name.clone(), value));
+        }
+    }
+    if let Some(list) = section.get("steps") {
+        let list = list.as_array().ok_or("'steps' must be an array")?;
+        for step in list {
+            steps.push(parse_step(step)?);
+        }
+    }
+    Ok(())
+}
+
+/// Load `SCENARIO` for `instance`, installing its env values. Returns the
+/// scenario's name and step count, or `None` when no scenario is set.
+pub fn load(instance: u16) -> Result<Option<(String, usize)>, String> {
+    let Some(path) = config::var("SCENARIO") else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let root = json::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if root.as_object().is_none() {
+        return Err(format!("{}: a scenario must be a JSON object", path));
+    }
+
+    let mut env = Vec::new();
+    let mut steps = Vec::new();
+    collect(&root, &mut env, &mut steps).map_err(|e| format!("{}: {}", path, e))?;
+    if let Some(entry) = root.get("instances").and_then(|i| i.get(&instance.to_string())) {
+        collect(entry, &mut env, &mut steps).map_err(|e| format!("{}: instance {}: {}", path, instance, e))?;
+    }
+    steps.sort_by_key(|step| step.at);
+
+    let name = root.get("name").and_then(Value::as_str).unwrap_or(&path).to_string();
+    let count = steps.len();
+    config::set_scenario(env);
+    let _ = STEPS.set(steps);
+    Ok(Some((name, count)))
+}
+
+/// Run the loaded timeline, timed from `started`, serving endpoint commands
+/// through `route`.
+pub fn start<F>(instance: u16, started: Instant, route: F)
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let Some(steps) = STEPS.get() else {
+        return;
+    };
+    let route = Arc::new(route);
+
+    for step in steps {
+        let route = Arc::clone(&route);
+        thread::spawn(move || {
+            let mut next = started + step.at;
+            loop {
+                thread::sleep(next.saturating_duration_since(Instant::now()));
+                let reply = console::execute(&step.command, instance, &*route).unwrap_or_else(|e| format!("error: {}", e));
+                info!("🎬 t={}ms {}: {}", started.elapsed().as_millis(), step.command, reply);
+
+                let Some(every) = step.every else {
+                    return;
+                };
+                next += every;
+                if step.until.is_some_and(|until| next > started + until) {
+                    return;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,132 @@
+//! What happens on SIGTERM/SIGINT, selected by `SHUTDOWN_BEHAVIOR`:
+//! - `graceful`: stop accepting, wait up to `DRAIN_MS` for in-flight requests, exit 0
+//! - `ignore`: log the signal and keep serving
+//! - `slow`: stop accepting, then stall for `SHUTDOWN_SLOW_MS` so TSPM's
+//!   `killTimeout` expires and it has to escalate to SIGKILL
+
+use std::os::raw::c_int;
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::signals;
+use crate::sys;
+
+const DRAIN_POLL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behavior {
+    Graceful,
+    Ignore,
+    Slow,
+}
+
+impl FromStr for Behavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "graceful" => Ok(Behavior::Graceful),
+            "ignore" => Ok(Behavior::Ignore),
+            "slow" => Ok(Behavior::Slow),
+            other => Err(format!(
+                "unknown shutdown behavior '{}' (expected graceful, ignore or slow)",
+                other
+            )),
+        }
+    }
+}
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Marks one request as in flight until dropped.
+pub struct InFlight(());
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn track() -> InFlight {
+    IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+    InFlight(())
+}
+
+pub fn in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::SeqCst)
+}
+
+/// Whether shutdown has begun, so keep-alive connections should be closed
+/// after their current request.
+pub fn stopping() -> bool {
+    STOPPING.load(Ordering::SeqCst)
+}
+
+pub fn behavior() -> Behavior {
+    config::parse_or("SHUTDOWN_BEHAVIOR", Behavior::Graceful)
+}
+
+/// Decide whether `sig` should stop the accept loop.
+pub fn should_stop(sig: c_int) -> bool {
+    if behavior() == Behavior::Ignore {
+        warn!("🛡️  Ignoring {} (SHUTDOWN_BEHAVIOR=ignore)", sys::signal_name(sig));
+        return false;
+    }
+    true
+}
+
+/// Sleep for `duration` before the server is listening, still stopping on
+/// SIGTERM/SIGINT so a slow start can be killed like any other.
+pub fn pause(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    loop {
+        if let Some(sig) = signals::take(&[sys::SIGTERM, sys::SIGINT]) {
+            if should_stop(sig) {
+                finish(sig);
+            }
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        thread::sleep(left.min(DRAIN_POLL));
+    }
+}
+
+/// Finish shutting down once the listener has been closed. Never returns.
+pub fn finish(sig: c_int) -> ! {
+    let name = sys::signal_name(sig);
+    STOPPING.store(true, Ordering::SeqCst);
+
+    if behavior() == Behavior::Slow {
+        let stall: u64 = config::parse_or("SHUTDOWN_SLOW_MS", 60_000);
+        info!("🐢 Received {}, stalling shutdown for {}ms", name, stall);
+        thread::sleep(Duration::from_millis(stall));
+        info!("🐢 Slow shutdown complete");
+        process::exit(0);
+    }
+
+    let drain: u64 = config::parse_or("DRAIN_MS", 3000);
+    info!(
+        "🛑 Received {}, draining {} in-flight request(s) for up to {}ms",
+        name,
+        in_flight(),
+        drain
+    );
+
+    let deadline = Instant::now() + Duration::from_millis(drain);
+    while in_flight() > 0 && Instant::now() < deadline {
+        thread::sleep(DRAIN_POLL);
+    }
+
+    match in_flight() {
+        0 => info!("🛑 Drain complete, exiting"),
+        n => warn!("🛑 Drain window elapsed with {} request(s) still in flight, exiting", n),
+    }
+    process::exit(0);
+}
@@ -0,0 +1,28 @@
+//! Signal capture. The handler only records which signals arrived; the accept
+//! loop polls for them and does the real work outside signal context.
+
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::sys;
+
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn record(sig: c_int) {
+    PENDING.fetch_or(1 << sig, Ordering::SeqCst);
+}
+
+/// Capture `signals` instead of letting their default action run.
+pub fn install(signals: &[c_int]) {
+    for &sig in signals {
+        sys::set_signal_handler(sig, record);
+    }
+}
+
+/// Consume the first pending signal out of `signals`, if any.
+pub fn take(signals: &[c_int]) -> Option<c_int> {
+    signals.iter().copied().find(|&sig| {
+        let bit = 1 << sig;
+        PENDING.fetch_and(!bit, Ordering::SeqCst) & bit != 0
+    })
+}
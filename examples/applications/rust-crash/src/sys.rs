@@ -0,0 +1,254 @@
+//! Thin libc bindings so the fixture stays dependency-free.
+
+use std::ffi::c_void;
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::os::fd::FromRawFd;
+use std::os::raw::c_int;
+
+pub const PROT_READ: c_int = 0x1;
+pub const PROT_WRITE: c_int = 0x2;
+pub const MAP_PRIVATE: c_int = 0x02;
+#[cfg(target_os = "linux")]
+pub const MAP_ANONYMOUS: c_int = 0x20;
+#[cfg(not(target_os = "linux"))]
+pub const MAP_ANONYMOUS: c_int = 0x1000;
+#[cfg(target_os = "linux")]
+pub const MAP_NORESERVE: c_int = 0x4000;
+#[cfg(not(target_os = "linux"))]
+pub const MAP_NORESERVE: c_int = 0x40;
+pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+pub const SIGHUP: c_int = 1;
+pub const SIGINT: c_int = 2;
+pub const SIGQUIT: c_int = 3;
+pub const SIGABRT: c_int = 6;
+pub const SIGKILL: c_int = 9;
+#[cfg(target_os = "linux")]
+pub const SIGUSR1: c_int = 10;
+#[cfg(not(target_os = "linux"))]
+pub const SIGUSR1: c_int = 30;
+pub const SIGSEGV: c_int = 11;
+pub const SIGTERM: c_int = 15;
+const SIG_DFL: usize = 0;
+
+#[cfg(target_os = "linux")]
+const AF_INET6: c_int = 10;
+#[cfg(not(target_os = "linux"))]
+const AF_INET6: c_int = 30;
+const AF_INET: c_int = 2;
+const SOCK_STREAM: c_int = 1;
+const F_SETFD: c_int = 2;
+const FD_CLOEXEC: c_int = 1;
+#[cfg(target_os = "linux")]
+const SOL_SOCKET: c_int = 1;
+#[cfg(not(target_os = "linux"))]
+const SOL_SOCKET: c_int = 0xffff;
+#[cfg(target_os = "linux")]
+const SO_REUSEADDR: c_int = 2;
+#[cfg(not(target_os = "linux"))]
+const SO_REUSEADDR: c_int = 0x4;
+#[cfg(target_os = "linux")]
+const SO_REUSEPORT: c_int = 15;
+#[cfg(not(target_os = "linux"))]
+const SO_REUSEPORT: c_int = 0x200;
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: i64,
+    ) -> *mut c_void;
+    fn signal(signum: c_int, handler: usize) -> usize;
+    fn kill(pid: c_int, sig: c_int) -> c_int;
+    fn setsid() -> c_int;
+    fn getuid() -> u32;
+    fn geteuid() -> u32;
+    fn getgid() -> u32;
+    fn getegid() -> u32;
+    fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+    fn setsockopt(fd: c_int, level: c_int, name: c_int, value: *const c_void, len: u32) -> c_int;
+    fn bind(fd: c_int, addr: *const u8, len: u32) -> c_int;
+    fn listen(fd: c_int, backlog: c_int) -> c_int;
+    fn close(fd: c_int) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+}
+
+/// Map `len` bytes of anonymous, private, read-write memory.
+///
+/// The mapping is never unmapped: every caller in this fixture leaks on purpose.
+pub fn map_anonymous(len: usize, extra_flags: c_int) -> Option<*mut u8> {
+    // SAFETY: anonymous mapping with no address hint and no backing fd.
+    let ptr = unsafe {
+        mmap(
+            std::ptr::null_mut(),
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS | extra_flags,
+            -1,
+            0,
+        )
+    };
+    (ptr != MAP_FAILED).then_some(ptr as *mut u8)
+}
+
+/// Bind a listening TCP socket with `SO_REUSEPORT`, so several processes can
+/// share `addr` and the kernel spreads connections across them.
+pub fn bind_reuseport(addr: SocketAddr) -> io::Result<TcpListener> {
+    let (domain, sockaddr) = encode_sockaddr(addr);
+    let check = |ret: c_int| if ret < 0 { Err(io::Error::last_os_error()) } else { Ok(ret) };
+
+    // SAFETY: plain socket calls on a descriptor we own; `sockaddr` outlives
+    // the `bind` call and its length is passed alongside.
+    unsafe {
+        let fd = check(socket(domain, SOCK_STREAM, 0))?;
+        let one: c_int = 1;
+        let setup = check(setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &one as *const c_int as *const c_void, 4))
+            .and_then(|_| check(setsockopt(fd, SOL_SOCKET, SO_REUSEPORT, &one as *const c_int as *const c_void, 4)))
+            .and_then(|_| check(bind(fd, sockaddr.as_ptr(), sockaddr.len() as u32)))
+            .and_then(|_| check(listen(fd, 128)))
+            .and_then(|_| set_cloexec(fd));
+        if let Err(e) = setup {
+            close(fd);
+            return Err(e);
+        }
+        Ok(TcpListener::from_raw_fd(fd))
+    }
+}
+
+/// Keep `fd` out of processes we spawn, so workers cannot hold a listening
+/// socket open after we exit.
+pub fn set_cloexec(fd: c_int) -> io::Result<c_int> {
+    // SAFETY: F_SETFD only changes descriptor flags.
+    match unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } {
+        ret if ret < 0 => Err(io::Error::last_os_error()),
+        ret => Ok(ret),
+    }
+}
+
+/// Lay out a `sockaddr_in` / `sockaddr_in6`. BSDs lead with a length byte and
+/// a one-byte family; Linux uses a two-byte family.
+fn encode_sockaddr(addr: SocketAddr) -> (c_int, Vec<u8>) {
+    let (domain, len) = match addr {
+        SocketAddr::V4(_) => (AF_INET, 16),
+        SocketAddr::V6(_) => (AF_INET6, 28),
+    };
+    let mut out = Vec::with_capacity(len);
+    if cfg!(target_os = "linux") {
+        out.extend_from_slice(&(domain as u16).to_ne_bytes());
+    } else {
+        out.extend_from_slice(&[len as u8, domain as u8]);
+    }
+    out.extend_from_slice(&addr.port().to_be_bytes());
+    match addr {
+        SocketAddr::V4(v4) => out.extend_from_slice(&v4.ip().octets()),
+        SocketAddr::V6(v6) => {
+            out.extend_from_slice(&v6.flowinfo().to_ne_bytes());
+            out.extend_from_slice(&v6.ip().octets());
+            out.extend_from_slice(&v6.scope_id().to_ne_bytes());
+        }
+    }
+    out.resize(len, 0);
+    (domain, out)
+}
+
+/// Real and effective user IDs.
+pub fn user_ids() -> (u32, u32) {
+    // SAFETY: these calls always succeed and touch no memory.
+    unsafe { (getuid(), geteuid()) }
+}
+
+/// Real and effective group IDs.
+pub fn group_ids() -> (u32, u32) {
+    // SAFETY: as above.
+    unsafe { (getgid(), getegid()) }
+}
+
+/// Resident set size of this process in bytes, when `/proc` is available.
+pub fn resident_bytes() -> Option<u64> {
+    proc_status_field("VmRSS:").map(|kb| kb * 1024)
+}
+
+/// Number of threads in this process (Linux only).
+pub fn thread_count() -> Option<u64> {
+    proc_status_field("Threads:")
+}
+
+/// First number on the `/proc/self/status` line starting with `field`.
+fn proc_status_field(field: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with(field))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Route `sig` to `handler`, which must only touch async-signal-safe state.
+pub fn set_signal_handler(sig: c_int, handler: extern "C" fn(c_int)) {
+    // SAFETY: installing a handler has no memory-safety preconditions.
+    unsafe { signal(sig, handler as usize) };
+}
+
+/// Restore the default disposition for `sig`.
+pub fn reset_signal_handler(sig: c_int) {
+    // SAFETY: as above.
+    unsafe { signal(sig, SIG_DFL) };
+}
+
+/// Send `sig` to this process.
+pub fn raise(sig: c_int) {
+    // SAFETY: signalling ourselves has no memory-safety preconditions.
+    unsafe { kill(std::process::id() as c_int, sig) };
+}
+
+/// Whether `pid` still exists (including as a zombie).
+pub fn process_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only performs the existence/permission check.
+    unsafe { kill(pid as c_int, 0) == 0 }
+}
+
+/// Detach the calling process into a new session. Async-signal-safe, so it
+/// may run between `fork` and `exec`.
+pub fn new_session() -> std::io::Result<()> {
+    // SAFETY: `setsid` has no memory-safety preconditions.
+    if unsafe { setsid() } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+const SIGNAL_NAMES: &[(c_int, &str)] = &[
+    (SIGHUP, "SIGHUP"),
+    (SIGINT, "SIGINT"),
+    (SIGQUIT, "SIGQUIT"),
+    (SIGABRT, "SIGABRT"),
+    (SIGKILL, "SIGKILL"),
+    (SIGUSR1, "SIGUSR1"),
+    (SIGSEGV, "SIGSEGV"),
+    (SIGTERM, "SIGTERM"),
+];
+
+pub fn signal_name(sig: c_int) -> &'static str {
+    SIGNAL_NAMES
+        .iter()
+        .find(|(n, _)| *n == sig)
+        .map(|(_, name)| *name)
+        .unwrap_or("signal")
+}
+
+/// The names `signal_from_name` accepts, without the `SIG` prefix.
+pub fn signal_names() -> impl Iterator<Item = &'static str> {
+    SIGNAL_NAMES.iter().map(|(_, name)| &name[3..])
+}
+
+/// Parse `SEGV`, `SIGSEGV` or `segv` into a signal number.
+pub fn signal_from_name(name: &str) -> Option<c_int> {
+    let name = name.trim().to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNAL_NAMES
+        .iter()
+        .find(|(_, n)| n[3..] == *name)
+        .map(|(sig, _)| *sig)
+}
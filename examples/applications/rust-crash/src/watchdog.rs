@@ -0,0 +1,115 @@
+//! Watchdog heartbeats, the reference for a restart policy that catches a
+//! service which deadlocks while its port stays open and TCP checks pass.
+//!
+//! With `WATCHDOG_USEC` set, a heartbeat goes out every half interval, as
+//! sd_notify's `WATCHDOG=1` on `NOTIFY_SOCKET` and as a `TSPM_WATCHDOG` line
+//! on stdout (the channels `READY_NOTIFY` selects). `WATCHDOG_PID` is
+//! optional but must match when set.
+//!
+//! Heartbeats stop after `WATCHDOG_STOP_AFTER_MS`, or at runtime via
+//! `/admin/watchdog`, while the process keeps serving; SIGUSR1 resumes them.
+
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::config;
+use crate::http::{Request, Response};
+use crate::notify::{self, Channels};
+
+pub const STDOUT_SENTINEL: &str = "TSPM_WATCHDOG";
+/// Shortest gap between heartbeats, so a tiny `WATCHDOG_USEC` cannot spin.
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+static STOPPED: AtomicBool = AtomicBool::new(false);
+/// Bumped on every switch so a stale auto-resume timer does nothing.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The heartbeat deadline from `WATCHDOG_USEC`, if this process should send any.
+fn interval() -> Option<Duration> {
+    if let Some(pid) = env::var("WATCHDOG_PID").ok().and_then(|p| p.trim().parse::<u32>().ok()) {
+        if pid != process::id() {
+            debug!("Ignoring WATCHDOG_USEC meant for PID {}", pid);
+            return None;
+        }
+    }
+    let usec: u64 = config::var("WATCHDOG_USEC")?.trim().parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+pub fn stopped() -> bool {
+    STOPPED.load(Ordering::SeqCst)
+}
+
+/// Stop or resume heartbeats, flipping back after `revert_after` if given.
+pub fn set_stopped(stop: bool, revert_after: Option<Duration>) {
+    STOPPED.store(stop, Ordering::SeqCst);
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let label = |stop: bool| if stop { "stopped" } else { "resumed" };
+
+    match revert_after {
+        Some(after) => {
+            warn!("💓 Watchdog heartbeats {} for {}ms", label(stop), after.as_millis());
+            thread::spawn(move || {
+                thread::sleep(after);
+                if GENERATION.load(Ordering::SeqCst) == generation {
+                    STOPPED.store(!stop, Ordering::SeqCst);
+                    info!("⏱️  Watchdog override expired, heartbeats {}", label(!stop));
+                }
+            });
+        }
+        None if stop => warn!("💔 Watchdog heartbeats stopped"),
+        None => info!("💓 Watchdog heartbeats resumed"),
+    }
+}
+
+pub fn start_from_env() {
+    let Some(interval) = interval() else {
+        return;
+    };
+    let channels = notify::channels();
+    if channels == Channels::None {
+        warn!("WATCHDOG_USEC is set but READY_NOTIFY=none; no heartbeats will be sent");
+        return;
+    }
+    if let Some(ms) = config::var("WATCHDOG_STOP_AFTER_MS").and_then(|s| s.trim().parse::<u64>().ok()) {
+        let generation = GENERATION.load(Ordering::SeqCst);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(ms));
+            if GENERATION.load(Ordering::SeqCst) == generation {
+                set_stopped(true, None);
+            }
+        });
+    }
+
+    let period = (interval / 2).max(MIN_PERIOD);
+    info!("💓 Watchdog heartbeats every {}ms", period.as_millis());
+    thread::spawn(move || loop {
+        if !stopped() {
+            notify::announce(channels, "WATCHDOG=1", STDOUT_SENTINEL);
+        }
+        thread::sleep(period);
+    });
+}
+
+/// `GET /admin/watchdog?state=running|stopped&secs=N`
+pub fn admin(request: &Request) -> Result<Response, Response> {
+    let state = |stop: bool| Ok(Response::ok(format!("Watchdog: {}", if stop { "stopped" } else { "running" })));
+    let stop = match request.query("state") {
+        None => return state(stopped()),
+        Some("running" | "resume" | "on") => false,
+        Some("stopped" | "stop" | "off") => true,
+        Some(other) => {
+            return Err(Response::bad_request(format!(
+                "unknown watchdog state '{}' (expected running or stopped)",
+                other
+            )))
+        }
+    };
+    let secs = request.parse_query("secs")?.map(Duration::from_secs);
+
+    set_stopped(stop, secs);
+    state(stop)
+}
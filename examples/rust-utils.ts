@@ -10,10 +10,17 @@ import path from "path";
 export const RUST_PROJECT_DIR = "examples/applications/rust-crash";
 export const BINARY_REL_PATH = "target/release/rust-crash-app";
 
+/**
+ * Binary path for a cargo profile, e.g. "release-abort" (built with panic=abort)
+ */
+export function binaryRelPath(profile: string = "release"): string {
+    return `target/${profile}/rust-crash-app`;
+}
+
 /**
  * Helper to build the Rust application locally
  */
-export function buildRustApp(): boolean {
+export function buildRustApp(profile: string = "release"): boolean {
     console.log("🛠️  Compiling Rust application...");
     
     // Check for cargo availability
@@ -27,8 +34,8 @@ export function buildRustApp(): boolean {
 
     const rustProject = path.join(process.cwd(), RUST_PROJECT_DIR);
     
-    // Build release binary
-    const build = spawnSync(["cargo", "build", "--release"], {
+    // Build binary for the requested profile
+    const build = spawnSync(["cargo", "build", "--profile", profile], {
         cwd: rustProject,
         stdio: ["ignore", "inherit", "inherit"]
     });
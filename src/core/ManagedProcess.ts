@@ -13,6 +13,7 @@ import {
   MEMORY_CONFIG,
   SCRIPT_EXTENSIONS,
   TIMEOUTS,
+  READY_PROTOCOL,
   BUN_ENV_VARS,
   NODE_ENV_VARS,
   type RestartReason,
@@ -59,6 +60,9 @@ class LogBuffer {
   }
 }
 
+/** Longest unterminated stdout tail kept while looking for the ready line */
+const READY_LINE_LIMIT = 256;
+
 export class ManagedProcess {
   private subprocess?: Subprocess;
   private config: ProcessConfig;
@@ -73,6 +77,7 @@ export class ManagedProcess {
   private watcher?: FSWatcher;
   private memoryMonitorInterval?: Timer;
   private listenTimeoutTimer?: Timer;
+  private readyWaiter?: (outcome: 'ready' | 'exited') => void;
   private logBuffer: LogBuffer = new LogBuffer();
 
   constructor(config: ProcessConfig, instanceId = 0, eventEmitter?: EventEmitter) {
@@ -96,6 +101,7 @@ export class ManagedProcess {
    */
   async start(): Promise<void> {
     this.isManuallyStopped = false;
+    this.isReady = false;
     const name = this.fullProcessName;
     
     // Setup watcher if enabled and not already watching
@@ -254,8 +260,10 @@ export class ManagedProcess {
     // 5. Start memory monitoring if maxMemory is configured
     this.startMemoryMonitoring();
     
-    // Update state to running
-    this.setState(ProcessStateValues.RUNNING);
+    // Update state to running, unless it has to signal readiness first
+    if (!this.config.waitReady) {
+      this.setState(ProcessStateValues.RUNNING);
+    }
     
     // 4. Run postStart script if defined
     if (this.config.postStart) {
@@ -290,11 +298,43 @@ export class ManagedProcess {
     // Always stream stdout and stderr into the in-memory buffer.
     // File writing is a secondary optional concern handled inside the stream reader.
     if (this.subprocess.stdout instanceof ReadableStream) {
-      this.asyncStreamToBuffer(this.subprocess.stdout, stdoutPath ?? null, LOG_TYPE.STDOUT);
+      this.asyncStreamToBuffer(this.subprocess.stdout, stdoutPath ?? null, LOG_TYPE.STDOUT, true);
     }
     if (this.subprocess.stderr instanceof ReadableStream) {
       this.asyncStreamToBuffer(this.subprocess.stderr, stderrPath ?? null, LOG_TYPE.STDERR);
     }
+
+    if (this.config.waitReady) {
+      await this.waitForReady();
+    }
+  }
+
+  /**
+   * Hold `start()` until the process signals readiness, exits, or
+   * `listenTimeout` (default `READY_PROTOCOL.DEFAULT_TIMEOUT`) elapses, so
+   * start, restart and scale only move on once an instance can take traffic
+   */
+  private async waitForReady(): Promise<void> {
+    const timeout = this.config.listenTimeout || READY_PROTOCOL.DEFAULT_TIMEOUT;
+    const outcome = await new Promise<'ready' | 'exited' | 'timeout'>((resolve) => {
+      if (this.isReady) {
+        resolve('ready');
+        return;
+      }
+      this.readyWaiter = resolve;
+      this.listenTimeoutTimer = setTimeout(() => resolve('timeout'), timeout);
+    });
+    clearTimeout(this.listenTimeoutTimer);
+    this.listenTimeoutTimer = undefined;
+    this.readyWaiter = undefined;
+
+    if (outcome === 'exited' || this.currentState !== ProcessStateValues.STARTING) {
+      return;
+    }
+    if (outcome === 'timeout') {
+      log.warn(`${APP_CONSTANTS.LOG_PREFIX} Process ${this.fullProcessName} did not signal readiness within ${timeout}ms`);
+    }
+    this.setState(ProcessStateValues.RUNNING);
   }
 
   /**
@@ -338,10 +378,13 @@ export class ManagedProcess {
 
   /**
    * Signal that the process is ready (for waitReady)
-   * Applications can call this to indicate they're ready to accept traffic
+   * Applications can call this to indicate they're ready to accept traffic.
+   * Processes without IPC (e.g. native binaries) print
+   * `READY_PROTOCOL.STDOUT_SENTINEL` on its own line instead.
    */
   public markReady(): void {
     this.isReady = true;
+    this.readyWaiter?.('ready');
     log.info(`${APP_CONSTANTS.LOG_PREFIX} Process ${this.fullProcessName} marked as ready`);
     
     // Emit ready event
@@ -526,6 +569,7 @@ export class ManagedProcess {
       restartCount: this.restartCount,
       uptime,
       instanceId: this.instanceId,
+      ready: this.isReady,
     };
   }
   
@@ -621,15 +665,21 @@ export class ManagedProcess {
   /**
    * Stream process output into the in-memory log buffer.
    * Optionally also writes to a file when `filePath` is provided.
+   * With `detectReady`, a line consisting of the ready sentinel marks the
+   * process as ready.
    */
   private async asyncStreamToBuffer(
     stream: ReadableStream,
     filePath: string | null,
-    type: import("../utils/config/constants").LogType
+    type: import("../utils/config/constants").LogType,
+    detectReady = false
   ): Promise<void> {
     const reader = stream.getReader();
     const decoder = new TextDecoder();
     let bytesWritten = 0;
+    // Unterminated tail of the previous chunk, so a sentinel split across
+    // chunks (or following a partial line) is still matched
+    let pendingLine = '';
 
     try {
       while (true) {
@@ -640,6 +690,17 @@ export class ManagedProcess {
         // Split on newlines so each line is its own entry
         const lines = raw.split(/\r?\n/);
 
+        if (detectReady && !this.isReady) {
+          const complete = (pendingLine + raw).split(/\r?\n/);
+          const tail = complete.pop() ?? '';
+          // A long unterminated line can never be the sentinel; keep a
+          // non-matching stub instead of buffering all of it
+          pendingLine = tail.length > READY_LINE_LIMIT ? '\0' : tail;
+          if (complete.some(line => line.trim() === READY_PROTOCOL.STDOUT_SENTINEL)) {
+            this.markReady();
+          }
+        }
+
         for (const line of lines) {
           if (!line) continue;
 
@@ -765,6 +826,7 @@ export class ManagedProcess {
     error: Error | undefined
   ): Promise<void> {
     const name = this.fullProcessName;
+    this.readyWaiter?.('exited');
     
     // Emit process exit event
     this.eventEmitter.emit(createEvent(
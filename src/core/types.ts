@@ -69,6 +69,8 @@ export interface ProcessStatus {
   clusterGroup?: string;
   /** Health status */
   healthy?: boolean;
+  /** Whether the process has signalled readiness */
+  ready?: boolean;
 }
 
 /**
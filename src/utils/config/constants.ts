@@ -477,6 +477,17 @@ export const APP_CONSTANTS = {
   DEFAULT_INTERPRETER: 'bun',
 } as const;
 
+/**
+ * Readiness protocol for processes that cannot call `markReady()` over IPC,
+ * such as native binaries
+ */
+export const READY_PROTOCOL = {
+  /** Line a process prints on stdout once it accepts traffic */
+  STDOUT_SENTINEL: 'TSPM_READY',
+  /** How long `waitReady` waits when `listenTimeout` is not set, in ms */
+  DEFAULT_TIMEOUT: 3000,
+} as const;
+
 /**
  * Memory monitoring constants
  */
@@ -0,0 +1,154 @@
+import { expect, it, describe, beforeAll, afterAll } from "bun:test";
+import { ManagedProcess } from "../../src/core/ManagedProcess";
+import type { ProcessConfig } from "../../src/core/types";
+import { rm, mkdir, writeFile } from "node:fs/promises";
+import { join } from "path";
+import { EventEmitter } from "../../src/utils/events";
+import { READY_PROTOCOL } from "../../src/utils/config/constants";
+
+const TEST_DIR = join(process.cwd(), "temp_test_ready");
+
+describe("ManagedProcess stdout readiness", () => {
+  beforeAll(async () => {
+    await rm(TEST_DIR, { recursive: true, force: true });
+    await mkdir(TEST_DIR, { recursive: true });
+  });
+
+  afterAll(async () => {
+    await rm(TEST_DIR, { recursive: true, force: true });
+  });
+
+  it("should mark the process ready when it prints the sentinel", async () => {
+    const scriptPath = join(TEST_DIR, "ready.ts");
+    await writeFile(
+      scriptPath,
+      `console.log('booting'); console.log('${READY_PROTOCOL.STDOUT_SENTINEL}'); setTimeout(() => {}, 5000);`
+    );
+
+    const config: ProcessConfig = {
+      name: "test-ready",
+      script: scriptPath,
+      autorestart: false,
+    };
+
+    const emitter = new EventEmitter();
+    const process = new ManagedProcess(config, 0, emitter);
+
+    let readyEvents = 0;
+    emitter.on("process:ready", () => {
+      readyEvents++;
+    });
+
+    await process.start();
+    expect(process.getStatus().ready).toBe(false);
+
+    await new Promise(resolve => setTimeout(resolve, 1000));
+
+    expect(readyEvents).toBe(1);
+    expect(process.getStatus().ready).toBe(true);
+    process.stop();
+  });
+
+  it("should not mark the process ready for other output", async () => {
+    const scriptPath = join(TEST_DIR, "not-ready.ts");
+    await writeFile(
+      scriptPath,
+      `console.log('not ${READY_PROTOCOL.STDOUT_SENTINEL} yet'); setTimeout(() => {}, 5000);`
+    );
+
+    const config: ProcessConfig = {
+      name: "test-not-ready",
+      script: scriptPath,
+      autorestart: false,
+    };
+
+    const emitter = new EventEmitter();
+    const process = new ManagedProcess(config, 0, emitter);
+
+    let readyEvents = 0;
+    emitter.on("process:ready", () => {
+      readyEvents++;
+    });
+
+    await process.start();
+    await new Promise(resolve => setTimeout(resolve, 1000));
+
+    expect(readyEvents).toBe(0);
+    expect(process.getStatus().ready).toBe(false);
+    process.stop();
+  });
+
+  it("should match a sentinel split across chunks after a partial line", async () => {
+    const scriptPath = join(TEST_DIR, "split.ts");
+    await writeFile(
+      scriptPath,
+      [
+        "process.stdout.write('a partial line');",
+        "setTimeout(() => process.stdout.write('\\nTSPM_'), 100);",
+        "setTimeout(() => process.stdout.write('READY\\n'), 300);",
+        "setTimeout(() => {}, 5000);",
+      ].join("\n")
+    );
+
+    const config: ProcessConfig = {
+      name: "test-split-ready",
+      script: scriptPath,
+      autorestart: false,
+    };
+
+    const process = new ManagedProcess(config, 0, new EventEmitter());
+    await process.start();
+    await new Promise(resolve => setTimeout(resolve, 1000));
+
+    expect(process.getStatus().ready).toBe(true);
+    process.stop();
+  });
+
+  it("should hold start() until the process is ready with waitReady", async () => {
+    const scriptPath = join(TEST_DIR, "wait-ready.ts");
+    await writeFile(
+      scriptPath,
+      `setTimeout(() => console.log('${READY_PROTOCOL.STDOUT_SENTINEL}'), 500); setTimeout(() => {}, 5000);`
+    );
+
+    const config: ProcessConfig = {
+      name: "test-wait-ready",
+      script: scriptPath,
+      autorestart: false,
+      waitReady: true,
+      listenTimeout: 4000,
+    };
+
+    const process = new ManagedProcess(config, 0, new EventEmitter());
+    const startedAt = Date.now();
+    await process.start();
+
+    expect(Date.now() - startedAt).toBeGreaterThanOrEqual(400);
+    expect(process.getStatus().ready).toBe(true);
+    expect(process.getState()).toBe("running");
+    process.stop();
+  });
+
+  it("should give up waiting after listenTimeout", async () => {
+    const scriptPath = join(TEST_DIR, "never-ready.ts");
+    await writeFile(scriptPath, "setTimeout(() => {}, 5000);");
+
+    const config: ProcessConfig = {
+      name: "test-never-ready",
+      script: scriptPath,
+      autorestart: false,
+      waitReady: true,
+      listenTimeout: 300,
+    };
+
+    const process = new ManagedProcess(config, 0, new EventEmitter());
+    const startedAt = Date.now();
+    await process.start();
+
+    expect(Date.now() - startedAt).toBeGreaterThanOrEqual(250);
+    expect(Date.now() - startedAt).toBeLessThan(3000);
+    expect(process.getStatus().ready).toBe(false);
+    expect(process.getState()).toBe("running");
+    process.stop();
+  });
+});